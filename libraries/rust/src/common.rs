@@ -1,29 +1,54 @@
-use anchor_lang::{prelude::Pubkey, AccountDeserialize};
+use anchor_lang::{prelude::Pubkey, Discriminator};
 use async_trait::async_trait;
-use lookup_table_registry::RegistryAccount;
+use lookup_table_registry::{discriminator, Decoded, RegistryAccount, RegistryPage};
+use solana_account_decoder::UiAccountEncoding;
 use solana_address_lookup_table_program_gateway::state::AddressLookupTable;
-use solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient};
+use solana_client::{
+    client_error::ClientError,
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+    rpc_request::MAX_MULTIPLE_ACCOUNTS,
+};
 use solana_sdk::{
     account::{Account, ReadableAccount},
     transaction::TransactionError,
 };
 
-use crate::{Entry, LOOKUP_TABLE_REGISTRY_ID};
+use crate::{Entry, LOOKUP_TABLE_ID, LOOKUP_TABLE_REGISTRY_ID};
 
 #[derive(Debug, Clone)]
 pub struct Registry {
+    pub address: Pubkey,
     pub authority: Pubkey,
+    /// The authority that the address of the registry is derived from, which
+    /// is the authority that created it
+    pub seed_authority: Pubkey,
+    /// The namespace of the registry, empty if it is not in a namespace
+    pub namespace: String,
     pub version: u8,
     pub tables: Vec<Entry>,
+    /// The lookup tables of entries whose account is missing or is not owned
+    /// by the lookup table program, because they were closed outside the
+    /// registry. Their entries can be removed with
+    /// [crate::instructions::prune_entry].
+    pub stale: Vec<Pubkey>,
 }
 
 impl Registry {
+    /// Fetch the registry of an authority in a namespace with its lookup tables.
+    /// The empty namespace is the registry that is not in any namespace.
+    ///
+    /// A registry keeps the address derived from its original authority when
+    /// its authority is transferred, so this is the authority that created the
+    /// registry. [Registry::find_by_authority] finds the registries that an
+    /// authority owns after a transfer.
     pub async fn fetch(
         rpc: &(impl AccountReader + ?Sized),
         authority: &Pubkey,
+        namespace: &str,
     ) -> LookupRegistryResult<Self> {
-        let registry_address =
-            Pubkey::find_program_address(&[authority.as_ref()], &LOOKUP_TABLE_REGISTRY_ID).0;
+        let registry_address = crate::derive_registry_address(authority, namespace);
         let registry = match rpc.get_account(&registry_address).await {
             Ok(value) => value,
             Err(e) => match e {
@@ -35,14 +60,59 @@ impl Registry {
                 }
             },
         };
-        let registry = RegistryAccount::try_deserialize(&mut registry.data())?;
+        let registry = RegistryAccount::try_deserialize_any_version(registry.data())?;
+        Self::fetch_tables(rpc, registry_address, registry).await
+    }
+
+    /// Find the registries that an authority currently owns with their lookup
+    /// tables, including the registries that were transferred to it.
+    ///
+    /// This searches all the registry accounts of the program with
+    /// getProgramAccounts, which is expensive and restricted by some RPC nodes,
+    /// so [Registry::fetch] never falls back to it. Readers that don't implement
+    /// [AccountReader::find_registry_accounts] find no registries.
+    pub async fn find_by_authority(
+        rpc: &(impl AccountReader + ?Sized),
+        authority: &Pubkey,
+    ) -> LookupRegistryResult<Vec<Self>> {
+        let mut registries = vec![];
+        for (address, account) in rpc.find_registry_accounts(authority).await? {
+            let registry = RegistryAccount::try_deserialize_any_version(account.data())?;
+            registries.push(Self::fetch_tables(rpc, address, registry).await?);
+        }
+        Ok(registries)
+    }
+
+    /// Fetch the pages and the lookup tables of a registry account
+    async fn fetch_tables(
+        rpc: &(impl AccountReader + ?Sized),
+        registry_address: Pubkey,
+        registry: Decoded<RegistryAccount>,
+    ) -> LookupRegistryResult<Self> {
+        // Walk the overflow pages of the registry
+        let page_addresses = (1..=registry.pages)
+            .map(|page| crate::derive_registry_page_address(&registry_address, page))
+            .collect::<Vec<_>>();
+        let mut pages = Vec::with_capacity(page_addresses.len());
+        if !page_addresses.is_empty() {
+            let accounts = get_multiple_accounts_chunked(rpc, &page_addresses).await?;
+            for (address, account) in page_addresses.iter().zip(accounts) {
+                let Some(account) = account else {
+                    return Err(LookupRegistryError::RegistryNotFound(*address));
+                };
+                pages.push(RegistryPage::try_deserialize_with_tables(account.data())?);
+            }
+        }
 
+        // Deactivated lookup tables are fetched to find the stale ones, but
+        // are not part of the lookup tables of the registry
         let mut pubkeys = vec![];
         let addresses = registry
             .tables
             .iter()
+            .chain(pages.iter().flat_map(|page| page.tables.iter()))
             .filter(|entry| {
-                if entry.discriminator > 1 {
+                if entry.discriminator != discriminator::EMPTY {
                     pubkeys.push(entry.table);
                     true
                 } else {
@@ -51,33 +121,62 @@ impl Registry {
             })
             .collect::<Vec<_>>();
 
-        let accounts = rpc.get_multiple_accounts(&pubkeys).await.unwrap();
+        let accounts = get_multiple_accounts_chunked(rpc, &pubkeys).await?;
+        let mut stale = vec![];
         let tables = accounts
             .into_iter()
             .zip(addresses)
             .filter_map(|(account, entry)| {
-                let Some(account) = account else {
-                return None;
-            };
+                let Some(account) = account.filter(|account| account.owner == LOOKUP_TABLE_ID)
+                else {
+                    stale.push(entry.table);
+                    return None;
+                };
+                if entry.discriminator == discriminator::DEACTIVATED {
+                    return None;
+                }
                 let Ok(table) = AddressLookupTable::deserialize(account.data()) else {
-                return None;
-            };
+                    return None;
+                };
                 Some(Entry {
-                    discriminator: entry.discriminator,
+                    discriminator: entry.discriminator & !discriminator::FROZEN,
+                    frozen: entry.is_frozen(),
                     lookup_address: entry.table,
+                    label: String::from_utf8_lossy(entry.metadata.label()).into_owned(),
+                    program: entry.metadata.program,
+                    created_slot: entry.metadata.created_slot,
+                    closeable_at_slot: entry.closeable_at_slot(),
+                    close_recipient: entry.is_auto_close().then_some(entry.close_recipient),
                     addresses: table.addresses.iter().copied().collect(),
                 })
             })
             .collect();
 
         Ok(Self {
-            authority: *authority,
+            address: registry_address,
+            authority: registry.authority,
+            seed_authority: registry.seed_authority,
+            namespace: String::from_utf8_lossy(registry.namespace()).into_owned(),
             version: registry.version,
             tables,
+            stale,
         })
     }
 }
 
+/// Fetch accounts in requests of at most [MAX_MULTIPLE_ACCOUNTS] accounts,
+/// which is the most that an RPC node returns for a request
+pub async fn get_multiple_accounts_chunked(
+    rpc: &(impl AccountReader + ?Sized),
+    pubkeys: &[Pubkey],
+) -> Result<Vec<Option<Account>>, AccountReadError> {
+    let mut accounts = Vec::with_capacity(pubkeys.len());
+    for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        accounts.extend(rpc.get_multiple_accounts(chunk).await?);
+    }
+    Ok(accounts)
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum LookupRegistryError {
     #[error("Registry does not exist {0}")]
@@ -105,6 +204,16 @@ pub trait AccountReader: Send + Sync {
     ) -> Result<Vec<Option<Account>>, AccountReadError>;
 
     async fn get_account(&self, pubkey: &Pubkey) -> Result<Account, AccountReadError>;
+
+    /// Find the registry accounts whose current authority is the given
+    /// authority, see [Registry::find_by_authority]. Readers that can't search
+    /// accounts find none.
+    async fn find_registry_accounts(
+        &self,
+        _authority: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Account)>, AccountReadError> {
+        Ok(vec![])
+    }
 }
 
 #[async_trait]
@@ -119,7 +228,31 @@ impl AccountReader for RpcClient {
     }
 
     async fn get_account(&self, pubkey: &Pubkey) -> Result<Account, AccountReadError> {
-        <RpcClient>::get_account(self, pubkey)
+        <RpcClient>::get_account_with_commitment(self, pubkey, self.commitment())
+            .await?
+            .value
+            .ok_or(AccountReadError::AccountNotFound)
+    }
+
+    async fn find_registry_accounts(
+        &self,
+        authority: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Account)>, AccountReadError> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    0,
+                    &RegistryAccount::DISCRIMINATOR,
+                )),
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(8, authority.as_ref())),
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        <RpcClient>::get_program_accounts_with_config(self, &LOOKUP_TABLE_REGISTRY_ID, config)
             .await
             .map_err(Into::into)
     }
@@ -141,8 +274,56 @@ impl From<ClientError> for AccountReadError {
     }
 }
 
+impl From<AccountReadError> for LookupRegistryError {
+    fn from(value: AccountReadError) -> Self {
+        match value {
+            AccountReadError::AccountNotFound => {
+                LookupRegistryError::AccountReadError(anyhow::anyhow!("Account not found"))
+            }
+            AccountReadError::Custom(e) => LookupRegistryError::AccountReadError(e),
+        }
+    }
+}
+
 impl From<anyhow::Error> for AccountReadError {
     fn from(value: anyhow::Error) -> Self {
         AccountReadError::Custom(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// A reader that records the number of accounts of each request
+    #[derive(Default)]
+    struct RequestSizes(Mutex<Vec<usize>>);
+
+    #[async_trait]
+    impl AccountReader for RequestSizes {
+        async fn get_multiple_accounts(
+            &self,
+            pubkeys: &[Pubkey],
+        ) -> Result<Vec<Option<Account>>, AccountReadError> {
+            self.0.lock().unwrap().push(pubkeys.len());
+            Ok(vec![None; pubkeys.len()])
+        }
+
+        async fn get_account(&self, _: &Pubkey) -> Result<Account, AccountReadError> {
+            Err(AccountReadError::AccountNotFound)
+        }
+    }
+
+    #[tokio::test]
+    async fn get_multiple_accounts_in_chunks() {
+        let reader = RequestSizes::default();
+        let pubkeys = (0..254).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+        let accounts = get_multiple_accounts_chunked(&reader, &pubkeys)
+            .await
+            .unwrap();
+        assert_eq!(accounts.len(), pubkeys.len());
+        assert_eq!(*reader.0.lock().unwrap(), vec![100, 100, 54]);
+    }
+}
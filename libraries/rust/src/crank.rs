@@ -0,0 +1,186 @@
+//! Close the deactivated lookup tables of registries that anyone can close.
+//!
+//! An authority lets anyone close a lookup table by setting a close recipient
+//! with [crate::writer::LookupRegistryWriter::set_auto_close]. Once the
+//! authority deactivates the lookup table and it has cooled down, a crank
+//! closes it with [crate::instructions::close_deactivated_table], and its
+//! lamports go to the close recipient.
+
+use anchor_lang::prelude::Pubkey;
+use lookup_table_registry::{discriminator, RegistryAccount, RegistryEntry, RegistryPage};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{account::ReadableAccount, signer::Signer, transaction::Transaction};
+
+use crate::common::{get_multiple_accounts_chunked, LookupRegistryError, LookupRegistryResult};
+use crate::instructions::close_deactivated_table;
+
+/// A deactivated lookup table that anyone can close
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CloseableTable {
+    /// The registry account that the lookup table belongs to
+    pub registry: Pubkey,
+    /// The page of the registry that stores the lookup table, if any
+    pub registry_page: Option<Pubkey>,
+    /// The lookup table
+    pub lookup_table: Pubkey,
+    /// The recipient of the lookup table's lamports
+    pub recipient: Pubkey,
+}
+
+/// The lookup tables that a crank has tried to close
+#[derive(Debug, Default)]
+pub struct CrankResult {
+    /// The lookup tables that have been closed
+    pub closed: Vec<Pubkey>,
+    /// The lookup tables that could not be closed, with the error
+    pub failed: Vec<(Pubkey, LookupRegistryError)>,
+}
+
+/// Find the lookup tables of the registries that anyone can close at the
+/// current slot, and close them. Each lookup table is closed in its own
+/// transaction, so that a lookup table that fails to close doesn't prevent the
+/// others from closing.
+///
+/// The payer signs and pays for the transactions, but the lamports of the
+/// lookup tables go to their close recipients.
+pub async fn close_deactivated_tables(
+    rpc: &RpcClient,
+    registries: &[Pubkey],
+    payer: &dyn Signer,
+) -> LookupRegistryResult<CrankResult> {
+    let tables = find_closeable_tables(rpc, registries).await?;
+    let mut result = CrankResult::default();
+    for table in tables {
+        let ix = close_deactivated_table(
+            table.registry,
+            table.registry_page,
+            table.lookup_table,
+            table.recipient,
+        );
+        let hash = rpc.get_latest_blockhash().await?;
+        let transaction =
+            Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[payer], hash);
+        match rpc.send_and_confirm_transaction(&transaction).await {
+            Ok(_) => result.closed.push(table.lookup_table),
+            Err(e) => result.failed.push((table.lookup_table, e.into())),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Find the lookup tables of the registries that anyone can close at the
+/// current slot, in the registry accounts and their pages
+pub async fn find_closeable_tables(
+    rpc: &RpcClient,
+    registries: &[Pubkey],
+) -> LookupRegistryResult<Vec<CloseableTable>> {
+    let slot = rpc.get_slot().await?;
+    let accounts = get_multiple_accounts_chunked(rpc, registries).await?;
+
+    let mut tables = vec![];
+    for (registry_address, account) in registries.iter().zip(accounts) {
+        let account = account.ok_or(LookupRegistryError::RegistryNotFound(*registry_address))?;
+        let registry = RegistryAccount::try_deserialize_any_version(account.data())?;
+        tables.extend(closeable_tables(
+            *registry_address,
+            None,
+            &registry.tables,
+            slot,
+        ));
+
+        let page_addresses = (1..=registry.pages)
+            .map(|page| crate::derive_registry_page_address(registry_address, page))
+            .collect::<Vec<_>>();
+        if page_addresses.is_empty() {
+            continue;
+        }
+        let pages = get_multiple_accounts_chunked(rpc, &page_addresses).await?;
+        for (page_address, page) in page_addresses.iter().zip(pages) {
+            let page = page.ok_or(LookupRegistryError::RegistryNotFound(*page_address))?;
+            let page = RegistryPage::try_deserialize_with_tables(page.data())?;
+            tables.extend(closeable_tables(
+                *registry_address,
+                Some(*page_address),
+                &page.tables,
+                slot,
+            ));
+        }
+    }
+
+    Ok(tables)
+}
+
+/// The entries of a registry account or page whose lookup tables anyone can
+/// close at a slot.
+///
+/// A lookup table is included once the slot reaches
+/// [RegistryEntry::closeable_at_slot], which is the earliest slot when it can
+/// be closed. Closing it can still fail if slots were skipped, in which case it
+/// is included again on the next run.
+pub fn closeable_tables(
+    registry: Pubkey,
+    registry_page: Option<Pubkey>,
+    tables: &[RegistryEntry],
+    slot: u64,
+) -> impl Iterator<Item = CloseableTable> + '_ {
+    tables
+        .iter()
+        .filter(move |entry| {
+            entry.is_auto_close()
+                && entry.discriminator == discriminator::DEACTIVATED
+                && matches!(
+                    entry.closeable_at_slot(),
+                    Some(closeable_at_slot) if closeable_at_slot <= slot
+                )
+        })
+        .map(move |entry| CloseableTable {
+            registry,
+            registry_page,
+            lookup_table: entry.table,
+            recipient: entry.close_recipient,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use lookup_table_registry::EntryMetadata;
+    use solana_sdk::slot_hashes::MAX_ENTRIES;
+
+    use super::*;
+
+    #[test]
+    fn closeable_tables_are_deactivated_cooled_down_and_auto_close() {
+        let recipient = Pubkey::new_unique();
+        let entry = |discriminator, deactivation_slot, close_recipient| RegistryEntry {
+            discriminator,
+            table: Pubkey::new_unique(),
+            metadata: EntryMetadata::default(),
+            deactivation_slot,
+            close_recipient,
+        };
+        let tables = [
+            entry(discriminator::DEACTIVATED, 100, recipient),
+            // Still cooling down
+            entry(discriminator::DEACTIVATED, 200, recipient),
+            // Only the authority can close it
+            entry(discriminator::DEACTIVATED, 100, Pubkey::default()),
+            // Active
+            entry(2, 0, recipient),
+            entry(discriminator::EMPTY, 0, Pubkey::default()),
+        ];
+        let registry = Pubkey::new_unique();
+        let slot = 100 + MAX_ENTRIES as u64 + 1;
+
+        let closeable = closeable_tables(registry, None, &tables, slot).collect::<Vec<_>>();
+        assert_eq!(
+            closeable,
+            vec![CloseableTable {
+                registry,
+                registry_page: None,
+                lookup_table: tables[0].table,
+                recipient,
+            }]
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! Decode the events emitted by the registry program from transaction logs.
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use lookup_table_registry::{
+    EntryPruned, RegistryInitialized, TableClosed, TableCreated, TableDeactivated, TableExtended,
+};
+
+use crate::LOOKUP_TABLE_REGISTRY_ID;
+
+const PROGRAM_DATA: &str = "Program data: ";
+
+/// An event emitted by the registry program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryEvent {
+    RegistryInitialized(RegistryInitialized),
+    TableCreated(TableCreated),
+    TableExtended(TableExtended),
+    TableDeactivated(TableDeactivated),
+    TableClosed(TableClosed),
+    EntryPruned(EntryPruned),
+}
+
+impl RegistryEvent {
+    /// Decode an event from the data that it was logged with. Returns `None`
+    /// if the data is not a registry event.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+        let (discriminator, mut event) = data.split_at(8);
+        let event = &mut event;
+        match discriminator {
+            d if d == RegistryInitialized::DISCRIMINATOR => {
+                AnchorDeserialize::deserialize(event).map(Self::RegistryInitialized)
+            }
+            d if d == TableCreated::DISCRIMINATOR => {
+                AnchorDeserialize::deserialize(event).map(Self::TableCreated)
+            }
+            d if d == TableExtended::DISCRIMINATOR => {
+                AnchorDeserialize::deserialize(event).map(Self::TableExtended)
+            }
+            d if d == TableDeactivated::DISCRIMINATOR => {
+                AnchorDeserialize::deserialize(event).map(Self::TableDeactivated)
+            }
+            d if d == TableClosed::DISCRIMINATOR => {
+                AnchorDeserialize::deserialize(event).map(Self::TableClosed)
+            }
+            d if d == EntryPruned::DISCRIMINATOR => {
+                AnchorDeserialize::deserialize(event).map(Self::EntryPruned)
+            }
+            _ => return None,
+        }
+        .ok()
+    }
+}
+
+/// Decode the registry events from the log messages of a transaction, in the
+/// order that they were emitted.
+///
+/// Only data logged while the registry program is executing is decoded, so
+/// that other programs can't log data that looks like a registry event.
+pub fn parse_logs<S: AsRef<str>>(logs: &[S]) -> Vec<RegistryEvent> {
+    let registry_id = LOOKUP_TABLE_REGISTRY_ID.to_string();
+    let mut invocations = Vec::<&str>::new();
+    let mut events = vec![];
+    for log in logs {
+        let log = log.as_ref();
+        if let Some(data) = log.strip_prefix(PROGRAM_DATA) {
+            if invocations.last() != Some(&registry_id.as_str()) {
+                continue;
+            }
+            let event = base64::decode(data)
+                .ok()
+                .and_then(|data| RegistryEvent::decode(&data));
+            events.extend(event);
+        } else if let Some(rest) = log.strip_prefix("Program ") {
+            // "Program <id> invoke [<depth>]" or "Program <id> success|failed: ..."
+            let mut words = rest.split(' ');
+            let program = words.next().unwrap_or_default();
+            match words.next() {
+                Some("invoke") => invocations.push(program),
+                Some("success") | Some("failed:") => {
+                    invocations.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::{prelude::Pubkey, Event};
+
+    use super::*;
+
+    fn log_data(event: &impl Event) -> String {
+        format!("{PROGRAM_DATA}{}", base64::encode(event.data()))
+    }
+
+    #[test]
+    fn parse_events_of_the_registry_program() {
+        let created = TableCreated {
+            registry: Pubkey::new_unique(),
+            table: Pubkey::new_unique(),
+            slot: 100,
+            discriminator: 2,
+        };
+        let extended = TableExtended {
+            registry: created.registry,
+            table: created.table,
+            count: 20,
+        };
+        let other = Pubkey::new_unique();
+        let logs = vec![
+            format!("Program {LOOKUP_TABLE_REGISTRY_ID} invoke [1]"),
+            "Program log: Instruction: CreateLookupTableWithAddresses".to_string(),
+            log_data(&created),
+            format!("Program {other} invoke [2]"),
+            log_data(&created),
+            format!("Program {other} success"),
+            log_data(&extended),
+            format!("Program {LOOKUP_TABLE_REGISTRY_ID} success"),
+            log_data(&extended),
+        ];
+
+        assert_eq!(
+            parse_logs(&logs),
+            vec![
+                RegistryEvent::TableCreated(created),
+                RegistryEvent::TableExtended(extended),
+            ]
+        );
+    }
+}
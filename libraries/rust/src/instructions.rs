@@ -1,32 +1,85 @@
 //! Build instructions to interact with the lookup registry.
 //! The instruction builder is useful if wanting to combine instructions,
 //! otherwise use [crate::registy::LookupRegistry].
+//!
+//! Instructions that operate on an entry of the registry take the page that
+//! stores the entry, where page 0 is the registry account itself.
 
 use anchor_lang::{InstructionData, ToAccountMetas};
 use lookup_table_registry::{
-    accounts as ix_accounts, instruction as ix_data, ID as LOOKUP_REGISTRY_ID,
+    accounts as ix_accounts, instruction as ix_data, NewLookupTable, ID as LOOKUP_REGISTRY_ID,
 };
 use solana_address_lookup_table_program_gateway::ID as LOOKUP_ID;
 use solana_sdk::{
-    instruction::Instruction, pubkey::Pubkey, system_program::ID as SYSTEM_PROGAM_ID,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program::ID as SYSTEM_PROGAM_ID,
 };
 
 /// An instruction builder of the lookup table registry program.
 pub struct InstructionBuilder {
-    /// The authority that owns the lookup table
+    /// The authority that owns the lookup table, or a delegate of the registry
+    /// authority. A delegate uses the registry authority as the seed authority.
     pub authority: Pubkey,
     /// The payer of transaction costs and rent
     pub payer: Pubkey,
+    /// The key that the registry address is derived from. This is the authority,
+    /// unless the authority of the registry has been transferred.
+    pub seed_authority: Pubkey,
+    /// The authority of the lookup tables being changed, if it is neither the
+    /// registry account nor the authority. Lookup tables created before version
+    /// 1 keep the authority that created them after the registry is transferred.
+    pub table_authority: Option<Pubkey>,
+    /// The namespace of the registry, empty if the registry is not in a namespace
+    pub namespace: String,
+    /// The other signers of a multisig authority, when the authority is one of
+    /// its signers. They sign the instructions that need the authority.
+    pub cosigners: Vec<Pubkey>,
 }
 
 impl InstructionBuilder {
     /// Creates a new instruction builder
     pub fn new(authority: Pubkey, payer: Pubkey) -> Self {
-        Self { authority, payer }
+        Self {
+            authority,
+            payer,
+            seed_authority: authority,
+            table_authority: None,
+            namespace: String::new(),
+            cosigners: vec![],
+        }
+    }
+
+    /// Use the registry of the seed authority in a namespace
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = namespace.to_string();
+        self
+    }
+
+    /// Use the registry whose address is derived from the seed authority
+    pub fn with_seed_authority(mut self, seed_authority: Pubkey) -> Self {
+        self.seed_authority = seed_authority;
+        self
+    }
+
+    /// Sign for lookup tables with a table authority other than the authority
+    pub fn with_table_authority(mut self, table_authority: Option<Pubkey>) -> Self {
+        self.table_authority = table_authority;
+        self
+    }
+
+    /// Sign with other signers of a multisig authority, together with the authority
+    pub fn with_cosigners(mut self, cosigners: &[Pubkey]) -> Self {
+        self.cosigners = cosigners.to_vec();
+        self
     }
 
-    /// Creates an instruction to initialize a lookup table registry.
+    /// Creates an instruction to initialize a lookup table registry, in the
+    /// namespace of the builder if it has one.
     pub fn init_registry(&self) -> Instruction {
+        if !self.namespace.is_empty() {
+            return self.init_namespaced_registry();
+        }
         let accounts = ix_accounts::InitRegistryAccount {
             authority: self.authority,
             payer: self.payer,
@@ -42,31 +95,220 @@ impl InstructionBuilder {
         }
     }
 
+    fn init_namespaced_registry(&self) -> Instruction {
+        let accounts = ix_accounts::InitNamespacedRegistryAccount {
+            authority: self.authority,
+            payer: self.payer,
+            registry_account: self.registry_address(),
+            system_program: SYSTEM_PROGAM_ID,
+        }
+        .to_account_metas(None);
+
+        Instruction {
+            program_id: LOOKUP_REGISTRY_ID,
+            accounts,
+            data: ix_data::InitNamespacedRegistryAccount {
+                namespace: self.namespace.clone(),
+            }
+            .data(),
+        }
+    }
+
+    /// Creates an instruction to migrate the registry account to the current
+    /// layout.
+    ///
+    /// Large accounts need the instruction to be repeated, see
+    /// [lookup_table_registry::registry_migration_len].
+    pub fn migrate_registry(&self) -> Instruction {
+        let accounts = self.signed_accounts(ix_accounts::MigrateRegistry {
+            authority: self.authority,
+            payer: self.payer,
+            registry_account: self.registry_address(),
+            system_program: SYSTEM_PROGAM_ID,
+        });
+
+        Instruction {
+            program_id: LOOKUP_REGISTRY_ID,
+            accounts,
+            data: ix_data::MigrateRegistry {}.data(),
+        }
+    }
+
+    /// Creates an instruction to add an overflow page to a lookup table registry.
+    ///
+    /// The page must be the next page of the registry, i.e. `registry.pages + 1`.
+    pub fn add_registry_page(&self, page: u8) -> Instruction {
+        let accounts = self.signed_accounts(ix_accounts::AddRegistryPage {
+            authority: self.authority,
+            payer: self.payer,
+            registry_account: self.registry_address(),
+            registry_page: self.registry_page_address(page),
+            system_program: SYSTEM_PROGAM_ID,
+        });
+
+        Instruction {
+            program_id: LOOKUP_REGISTRY_ID,
+            accounts,
+            data: ix_data::AddRegistryPage {}.data(),
+        }
+    }
+
+    /// Creates an instruction to propose a new authority for the registry.
+    ///
+    /// Proposing the default pubkey cancels a pending transfer.
+    pub fn propose_authority(&self, new_authority: Pubkey) -> Instruction {
+        let accounts = self.signed_accounts(ix_accounts::ProposeAuthority {
+            authority: self.authority,
+            registry_account: self.registry_address(),
+        });
+
+        Instruction {
+            program_id: LOOKUP_REGISTRY_ID,
+            accounts,
+            data: ix_data::ProposeAuthority { new_authority }.data(),
+        }
+    }
+
+    /// Creates an instruction for the authority to accept a proposed transfer
+    /// of the registry.
+    pub fn accept_authority(&self) -> Instruction {
+        let accounts = ix_accounts::AcceptAuthority {
+            new_authority: self.authority,
+            registry_account: self.registry_address(),
+        }
+        .to_account_metas(None);
+
+        Instruction {
+            program_id: LOOKUP_REGISTRY_ID,
+            accounts,
+            data: ix_data::AcceptAuthority {}.data(),
+        }
+    }
+
+    /// Creates an instruction to add a delegate that can sign for the authority,
+    /// or to update the permissions of an existing delegate.
+    ///
+    /// See [lookup_table_registry::permissions] for the permissions.
+    pub fn add_delegate(&self, delegate: Pubkey, permissions: u8) -> Instruction {
+        let accounts = self.signed_accounts(ix_accounts::UpdateDelegates {
+            authority: self.authority,
+            registry_account: self.registry_address(),
+        });
+
+        Instruction {
+            program_id: LOOKUP_REGISTRY_ID,
+            accounts,
+            data: ix_data::AddDelegate {
+                delegate,
+                permissions,
+            }
+            .data(),
+        }
+    }
+
+    /// Creates an instruction to set the signers and threshold of the multisig
+    /// authority of the registry. No signers and a threshold of 0 leave
+    /// multisig mode.
+    pub fn set_multisig(&self, signers: &[Pubkey], threshold: u8) -> Instruction {
+        let accounts = self.signed_accounts(ix_accounts::SetMultisig {
+            authority: self.authority,
+            registry_account: self.registry_address(),
+        });
+
+        Instruction {
+            program_id: LOOKUP_REGISTRY_ID,
+            accounts,
+            data: ix_data::SetMultisig {
+                signers: signers.to_vec(),
+                threshold,
+            }
+            .data(),
+        }
+    }
+
+    /// Creates an instruction to revoke a delegate of the authority.
+    pub fn revoke_delegate(&self, delegate: Pubkey) -> Instruction {
+        let accounts = self.signed_accounts(ix_accounts::UpdateDelegates {
+            authority: self.authority,
+            registry_account: self.registry_address(),
+        });
+
+        Instruction {
+            program_id: LOOKUP_REGISTRY_ID,
+            accounts,
+            data: ix_data::RevokeDelegate { delegate }.data(),
+        }
+    }
+
+    /// Creates an instruction to close an empty registry account, sending its
+    /// rent to the payer. All pages must be closed first.
+    pub fn close_registry(&self) -> Instruction {
+        self.close_registry_account(None)
+    }
+
+    /// Creates an instruction to close the last page of the registry account,
+    /// sending its rent to the payer. The page must be empty.
+    pub fn close_registry_page(&self, page: u8) -> Instruction {
+        self.close_registry_account(self.page_account(page))
+    }
+
+    fn close_registry_account(&self, registry_page: Option<Pubkey>) -> Instruction {
+        let accounts = self.signed_accounts(ix_accounts::CloseRegistryAccount {
+            authority: self.authority,
+            recipient: self.payer,
+            registry_account: self.registry_address(),
+            registry_page,
+        });
+
+        Instruction {
+            program_id: LOOKUP_REGISTRY_ID,
+            accounts,
+            data: ix_data::CloseRegistryAccount {}.data(),
+        }
+    }
+
+    /// Creates an instruction to remove the empty entries of the registry
+    /// account, or of a page if it is not 0, sending the rent that is no longer
+    /// needed to the registry authority, which is the recipient.
+    pub fn compact_registry(&self, page: u8, recipient: Pubkey) -> Instruction {
+        let accounts = self.signed_accounts(ix_accounts::CompactRegistry {
+            authority: self.authority,
+            recipient,
+            registry_account: self.registry_address(),
+            registry_page: self.page_account(page),
+        });
+
+        Instruction {
+            program_id: LOOKUP_REGISTRY_ID,
+            accounts,
+            data: ix_data::CompactRegistry {}.data(),
+        }
+    }
+
     /// Instruction to create a lookup table.
     ///
+    /// The discriminator tags the lookup table, and must be greater than
+    /// [lookup_table_registry::discriminator::DEACTIVATED].
+    ///
     /// Returns the address of the lookup table with the instruction to create it.
     pub fn create_lookup_table(
         &self,
         recent_slot: u64,
-        // Not required, kept for future compat purposes
-        _discriminator: u64,
+        discriminator: u64,
+        page: u8,
     ) -> (Instruction, Pubkey) {
-        // Get slot
+        // The registry account is the authority of the lookup table
         let lookup_table =
-            solana_address_lookup_table_program_gateway::instruction::derive_lookup_table_address(
-                &self.authority,
-                recent_slot,
-            )
-            .0;
-        let accounts = ix_accounts::CreateLookupTable {
+            crate::derive_lookup_table_address(&self.registry_address(), recent_slot);
+        let accounts = self.signed_accounts(ix_accounts::CreateLookupTable {
             authority: self.authority,
             payer: self.payer,
             registry_account: self.registry_address(),
+            registry_page: self.page_account(page),
             lookup_table,
             address_lookup_table_program: LOOKUP_ID,
             system_program: SYSTEM_PROGAM_ID,
-        }
-        .to_account_metas(None);
+        });
 
         (
             Instruction {
@@ -74,7 +316,7 @@ impl InstructionBuilder {
                 accounts,
                 data: ix_data::CreateLookupTable {
                     recent_slot,
-                    _discriminator: 0,
+                    discriminator,
                 }
                 .data(),
             },
@@ -82,17 +324,158 @@ impl InstructionBuilder {
         )
     }
 
-    /// Creates an instruction to remove a lookup table.
-    pub fn remove_lookup_table(&self, lookup_table: Pubkey) -> Instruction {
-        let accounts = ix_accounts::RemoveLookupTable {
+    /// Instruction to create a lookup table, and extend it with addresses.
+    ///
+    /// The number of addresses is limited by the size of a transaction.
+    /// Repeated addresses are skipped if `skip_duplicates` is set, or the
+    /// program fails otherwise.
+    ///
+    /// Returns the address of the lookup table with the instruction to create it.
+    pub fn create_lookup_table_with_addresses(
+        &self,
+        recent_slot: u64,
+        discriminator: u64,
+        addresses: &[Pubkey],
+        skip_duplicates: bool,
+        page: u8,
+    ) -> (Instruction, Pubkey) {
+        let lookup_table =
+            crate::derive_lookup_table_address(&self.registry_address(), recent_slot);
+        let accounts = self.signed_accounts(ix_accounts::CreateLookupTable {
             authority: self.authority,
-            recipient: self.payer,
+            payer: self.payer,
             registry_account: self.registry_address(),
+            registry_page: self.page_account(page),
+            lookup_table,
+            address_lookup_table_program: LOOKUP_ID,
+            system_program: SYSTEM_PROGAM_ID,
+        });
+
+        (
+            Instruction {
+                program_id: LOOKUP_REGISTRY_ID,
+                accounts,
+                data: ix_data::CreateLookupTableWithAddresses {
+                    recent_slot,
+                    discriminator,
+                    addresses: addresses.to_vec(),
+                    skip_duplicates,
+                }
+                .data(),
+            },
             lookup_table,
+        )
+    }
+
+    /// Instruction to create several lookup tables.
+    ///
+    /// The recent slots of the tables must be increasing.
+    ///
+    /// Returns the addresses of the lookup tables with the instruction to create them.
+    pub fn create_lookup_tables(
+        &self,
+        tables: &[NewLookupTable],
+        page: u8,
+    ) -> (Instruction, Vec<Pubkey>) {
+        let lookup_tables = tables
+            .iter()
+            .map(|table| {
+                crate::derive_lookup_table_address(&self.registry_address(), table.recent_slot)
+            })
+            .collect::<Vec<_>>();
+        let mut accounts = ix_accounts::CreateLookupTables {
+            authority: self.authority,
+            payer: self.payer,
+            registry_account: self.registry_address(),
+            registry_page: self.page_account(page),
             address_lookup_table_program: LOOKUP_ID,
             system_program: SYSTEM_PROGAM_ID,
         }
         .to_account_metas(None);
+        accounts.extend(
+            lookup_tables
+                .iter()
+                .map(|table| AccountMeta::new(*table, false)),
+        );
+        accounts.extend(self.cosigner_accounts());
+
+        (
+            Instruction {
+                program_id: LOOKUP_REGISTRY_ID,
+                accounts,
+                data: ix_data::CreateLookupTables {
+                    tables: tables.to_vec(),
+                }
+                .data(),
+            },
+            lookup_tables,
+        )
+    }
+
+    /// Creates an instruction to import a lookup table that was created outside
+    /// of the registry. The authority of the lookup table must be the registry
+    /// account or the authority.
+    pub fn import_lookup_table(
+        &self,
+        lookup_table: Pubkey,
+        discriminator: u64,
+        page: u8,
+    ) -> Instruction {
+        let accounts = self.signed_accounts(ix_accounts::ImportLookupTable {
+            authority: self.authority,
+            payer: self.payer,
+            registry_account: self.registry_address(),
+            registry_page: self.page_account(page),
+            lookup_table,
+            system_program: SYSTEM_PROGAM_ID,
+        });
+
+        Instruction {
+            program_id: LOOKUP_REGISTRY_ID,
+            accounts,
+            data: ix_data::ImportLookupTable { discriminator }.data(),
+        }
+    }
+
+    /// Creates an instruction to set the label of a lookup table, and the
+    /// program that it is used with.
+    pub fn set_entry_metadata(
+        &self,
+        lookup_table: Pubkey,
+        label: &str,
+        program: Pubkey,
+        page: u8,
+    ) -> Instruction {
+        let accounts = self.signed_accounts(ix_accounts::SetEntryMetadata {
+            authority: self.authority,
+            registry_account: self.registry_address(),
+            registry_page: self.page_account(page),
+            lookup_table,
+        });
+
+        Instruction {
+            program_id: LOOKUP_REGISTRY_ID,
+            accounts,
+            data: ix_data::SetEntryMetadata {
+                label: label.to_string(),
+                program,
+            }
+            .data(),
+        }
+    }
+
+    /// Creates an instruction to remove a lookup table.
+    pub fn remove_lookup_table(&self, lookup_table: Pubkey, page: u8) -> Instruction {
+        let accounts = self.signed_accounts(ix_accounts::RemoveLookupTable {
+            authority: self.authority,
+            table_authority: self.table_authority,
+            recipient: self.payer,
+            registry_account: self.registry_address(),
+            registry_page: self.page_account(page),
+            lookup_table,
+            address_lookup_table_program: LOOKUP_ID,
+            system_program: SYSTEM_PROGAM_ID,
+        });
 
         Instruction {
             program_id: LOOKUP_REGISTRY_ID,
@@ -101,41 +484,167 @@ impl InstructionBuilder {
         }
     }
 
+    /// Creates an instruction to freeze a lookup table.
+    ///
+    /// A frozen lookup table can no longer be extended or removed.
+    pub fn freeze_lookup_table(&self, lookup_table: Pubkey, page: u8) -> Instruction {
+        let accounts = self.signed_accounts(ix_accounts::FreezeLookupTable {
+            authority: self.authority,
+            table_authority: self.table_authority,
+            registry_account: self.registry_address(),
+            registry_page: self.page_account(page),
+            lookup_table,
+            address_lookup_table_program: LOOKUP_ID,
+        });
+
+        Instruction {
+            program_id: LOOKUP_REGISTRY_ID,
+            accounts,
+            data: ix_data::FreezeLookupTable.data(),
+        }
+    }
+
+    /// Creates an instruction to set the recipient of a lookup table's lamports,
+    /// so that anyone can close it with [close_deactivated_table] once it has
+    /// been deactivated and has cooled down. No recipient means that only the
+    /// authority can close it.
+    ///
+    /// The registry account must be the authority of the lookup table.
+    pub fn set_auto_close(
+        &self,
+        lookup_table: Pubkey,
+        close_recipient: Option<Pubkey>,
+        page: u8,
+    ) -> Instruction {
+        let accounts = self.signed_accounts(ix_accounts::SetAutoClose {
+            authority: self.authority,
+            registry_account: self.registry_address(),
+            registry_page: self.page_account(page),
+            lookup_table,
+        });
+
+        Instruction {
+            program_id: LOOKUP_REGISTRY_ID,
+            accounts,
+            data: ix_data::SetAutoClose { close_recipient }.data(),
+        }
+    }
+
     /// Creates an instruction to append addresses to a lookup table.
-    /// First inspects the lookup table to remove any duplicate addresses,
-    /// then appends the unique new addresses.
+    /// The program inspects the lookup table for addresses that are already
+    /// present, and skips them if `skip_duplicates` is set, or fails otherwise.
     ///
-    /// An error is returned if the addresses would exceed the lookup table's limit.
+    /// An error is returned if the addresses would exceed the lookup table's limit,
+    /// or if the discriminator is not the one the lookup table was created with.
     pub fn append_to_lookup_table(
         &self,
         lookup_table: Pubkey,
         addresses: &[Pubkey],
-        // Not required, kept for future compat purposes
-        _discriminator: u64,
+        discriminator: u64,
+        skip_duplicates: bool,
+        page: u8,
     ) -> Instruction {
-        let accounts = ix_accounts::AppendToLookupTable {
+        let accounts = self.signed_accounts(ix_accounts::AppendToLookupTable {
             authority: self.authority,
+            table_authority: self.table_authority,
             payer: self.payer,
             registry_account: self.registry_address(),
+            registry_page: self.page_account(page),
             lookup_table,
             address_lookup_table_program: LOOKUP_ID,
             system_program: SYSTEM_PROGAM_ID,
-        }
-        .to_account_metas(None);
+        });
 
         Instruction {
             program_id: LOOKUP_REGISTRY_ID,
             accounts,
             data: ix_data::AppendToLookupTable {
-                _discriminator: 0,
+                discriminator,
                 addresses: addresses.to_vec(),
+                skip_duplicates,
             }
             .data(),
         }
     }
 
-    /// Derive the address of the registry account using the authority.
+    /// Derive the address of the registry account using the seed authority and
+    /// the namespace.
     pub fn registry_address(&self) -> Pubkey {
-        Pubkey::find_program_address(&[self.authority.as_ref()], &LOOKUP_REGISTRY_ID).0
+        crate::derive_registry_address(&self.seed_authority, &self.namespace)
+    }
+
+    /// Derive the address of an overflow page of the registry account.
+    pub fn registry_page_address(&self, page: u8) -> Pubkey {
+        crate::derive_registry_page_address(&self.registry_address(), page)
+    }
+
+    /// The page account to pass to an instruction, none if the page is the registry account
+    fn page_account(&self, page: u8) -> Option<Pubkey> {
+        (page > 0).then(|| self.registry_page_address(page))
+    }
+
+    /// The accounts of an instruction that the authority signs, followed by
+    /// the cosigners
+    fn signed_accounts(&self, accounts: impl ToAccountMetas) -> Vec<AccountMeta> {
+        let mut accounts = accounts.to_account_metas(None);
+        accounts.extend(self.cosigner_accounts());
+        accounts
+    }
+
+    fn cosigner_accounts(&self) -> impl Iterator<Item = AccountMeta> + '_ {
+        self.cosigners
+            .iter()
+            .map(|cosigner| AccountMeta::new_readonly(*cosigner, true))
+    }
+}
+
+/// Creates an instruction to close a deactivated lookup table that has a close
+/// recipient, see [InstructionBuilder::set_auto_close]. Anyone can sign it, so
+/// it doesn't need an authority.
+///
+/// The registry page is the page that stores the lookup table, if any.
+pub fn close_deactivated_table(
+    registry: Pubkey,
+    registry_page: Option<Pubkey>,
+    lookup_table: Pubkey,
+    recipient: Pubkey,
+) -> Instruction {
+    let accounts = ix_accounts::CloseDeactivatedTable {
+        registry_account: registry,
+        registry_page,
+        lookup_table,
+        recipient,
+        address_lookup_table_program: LOOKUP_ID,
+    }
+    .to_account_metas(None);
+
+    Instruction {
+        program_id: LOOKUP_REGISTRY_ID,
+        accounts,
+        data: ix_data::CloseDeactivatedTable.data(),
+    }
+}
+
+/// Creates an instruction to remove the entry of a lookup table that was
+/// closed outside the registry, or whose account is no longer owned by the
+/// lookup table program. Anyone can sign it, so it doesn't need an authority.
+///
+/// The registry page is the page that stores the lookup table, if any.
+pub fn prune_entry(
+    registry: Pubkey,
+    registry_page: Option<Pubkey>,
+    lookup_table: Pubkey,
+) -> Instruction {
+    let accounts = ix_accounts::PruneEntry {
+        registry_account: registry,
+        registry_page,
+        lookup_table,
+    }
+    .to_account_metas(None);
+
+    Instruction {
+        program_id: LOOKUP_REGISTRY_ID,
+        accounts,
+        data: ix_data::PruneEntry.data(),
     }
 }
@@ -1,6 +1,7 @@
 use anchor_lang::prelude::Pubkey;
 use solana_sdk::address_lookup_table_account::AddressLookupTableAccount;
 
+pub mod events;
 pub mod instructions;
 #[cfg(feature = "client")]
 pub mod reader;
@@ -9,6 +10,8 @@ pub mod writer;
 
 #[cfg(feature = "client")]
 pub mod common;
+#[cfg(feature = "client")]
+pub mod crank;
 
 pub use lookup_table_registry::ID as LOOKUP_TABLE_REGISTRY_ID;
 pub use solana_address_lookup_table_program_gateway::ID as LOOKUP_TABLE_ID;
@@ -16,7 +19,21 @@ pub use solana_address_lookup_table_program_gateway::ID as LOOKUP_TABLE_ID;
 #[derive(Debug, Clone)]
 pub struct Entry {
     pub discriminator: u64,
+    /// Whether the lookup table has been frozen, in which case it can no longer change
+    pub frozen: bool,
     pub lookup_address: Pubkey,
+    /// A human-readable label of the lookup table, empty if it has none
+    pub label: String,
+    /// The program that the lookup table is used with, or the default pubkey
+    pub program: Pubkey,
+    /// The slot when the lookup table was added to the registry
+    pub created_slot: u64,
+    /// The earliest slot when the lookup table can be closed, if it has been
+    /// deactivated
+    pub closeable_at_slot: Option<u64>,
+    /// The recipient of the lookup table's lamports when anyone closes it
+    /// after it has been deactivated, none if only the authority can close it
+    pub close_recipient: Option<Pubkey>,
     /// The list of addresses.
     ///
     /// It would be convenient to have this as a HashSet to remove duplicates,
@@ -43,3 +60,19 @@ pub fn derive_lookup_table_address(authority: &Pubkey, recent_block_slot: u64) -
     )
     .0
 }
+
+/// Derive the address of the registry account of an authority in a namespace.
+/// The empty namespace is the registry that is not in any namespace.
+pub fn derive_registry_address(authority: &Pubkey, namespace: &str) -> Pubkey {
+    Pubkey::find_program_address(
+        &[authority.as_ref(), namespace.as_bytes()],
+        &LOOKUP_TABLE_REGISTRY_ID,
+    )
+    .0
+}
+
+/// Derive the address of an overflow page of a registry account.
+/// Pages are numbered from 1.
+pub fn derive_registry_page_address(registry: &Pubkey, page: u8) -> Pubkey {
+    Pubkey::find_program_address(&[registry.as_ref(), &[page]], &LOOKUP_TABLE_REGISTRY_ID).0
+}
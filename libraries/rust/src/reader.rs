@@ -11,12 +11,18 @@ use solana_sdk::{
     address_lookup_table_account::AddressLookupTableAccount, instruction::Instruction,
 };
 
-use crate::common::{AccountReader, Registry};
+use crate::common::{AccountReader, LookupRegistryResult, Registry};
+
+/// The registries in the cache are keyed by their authority and namespace
+pub type RegistryKey = (Pubkey, String);
 
 /// A client suitable for querying instruction registries for authorities.
+///
+/// The registries of an authority are queried in a namespace, where the empty
+/// namespace is the registry that is not in any namespace.
 pub struct LookupRegistryReader<A> {
     rpc: A,
-    cache: Arc<RwLock<endorphin::HashMap<Pubkey, Registry, TTLPolicy>>>,
+    cache: Arc<RwLock<endorphin::HashMap<RegistryKey, Registry, TTLPolicy>>>,
 }
 
 impl<A: Clone> Clone for LookupRegistryReader<A> {
@@ -41,25 +47,33 @@ impl<A: Deref<Target = X>, X: AccountReader> LookupRegistryReader<A> {
     /// Fetch the latest registry addresses for specific authorities.
     ///
     /// Returns the authorities that were not found or otherwise incurred some error
-    pub async fn update_registries(&self, authorities: &[Pubkey]) -> Vec<Pubkey> {
+    pub async fn update_registries(&self, authorities: &[Pubkey], namespace: &str) -> Vec<Pubkey> {
         let mut errors = Vec::with_capacity(authorities.len());
         for authority in authorities {
-            let Ok(registry) = Registry::fetch(&*self.rpc, authority).await else {
+            let Ok(registry) = Registry::fetch(&*self.rpc, authority, namespace).await else {
                 errors.push(*authority);
                 continue;
             };
             let mut writer = self.cache.write().unwrap();
-            writer.insert(*authority, registry, Duration::from_secs(3600));
+            writer.insert(
+                (*authority, namespace.to_string()),
+                registry,
+                Duration::from_secs(3600),
+            );
         }
         errors
     }
 
     /// Returns all the lookup tables that are in the registries owned by the
     /// provided authorities.
-    pub async fn get_tables(&self, authorities: &[Pubkey]) -> Vec<AddressLookupTableAccount> {
+    pub async fn get_tables(
+        &self,
+        authorities: &[Pubkey],
+        namespace: &str,
+    ) -> Vec<AddressLookupTableAccount> {
         let mut ret = vec![];
         for authority in authorities {
-            if let Some(r) = self.get_registry(authority).await {
+            if let Some(r) = self.get_registry(authority, namespace).await {
                 ret.extend(r.tables.into_iter().map(Into::into));
             }
         }
@@ -72,6 +86,7 @@ impl<A: Deref<Target = X>, X: AccountReader> LookupRegistryReader<A> {
         &self,
         instructions: &[Instruction],
         authorities: &[Pubkey],
+        namespace: &str,
     ) -> FindAddressesResult {
         let mut accounts = HashSet::with_capacity(256);
         for ix in instructions {
@@ -86,7 +101,7 @@ impl<A: Deref<Target = X>, X: AccountReader> LookupRegistryReader<A> {
         let mut matches = vec![];
         for authority in authorities {
             let reader = self.cache.read().unwrap();
-            let Some(registry) = reader.get(authority) else {
+            let Some(registry) = reader.get(&(*authority, namespace.to_string())) else {
                 continue;
             };
             // We have a registry, find matches.
@@ -133,20 +148,46 @@ impl<A: Deref<Target = X>, X: AccountReader> LookupRegistryReader<A> {
         }
     }
 
-    pub async fn get_registry(&self, authority: &Pubkey) -> Option<Registry> {
+    /// Find the registries that an authority currently owns in all namespaces,
+    /// including the ones that were transferred to it, and cache them by the
+    /// authority that created them and their namespace. Returns these keys,
+    /// which the other methods of the reader take.
+    ///
+    /// This searches all the registry accounts of the program, see
+    /// [Registry::find_by_authority].
+    pub async fn find_registries(
+        &self,
+        authority: &Pubkey,
+    ) -> LookupRegistryResult<Vec<RegistryKey>> {
+        let registries = Registry::find_by_authority(&*self.rpc, authority).await?;
+        let mut writer = self.cache.write().unwrap();
+        Ok(registries
+            .into_iter()
+            .map(|registry| {
+                let key = (registry.seed_authority, registry.namespace.clone());
+                writer.insert(key.clone(), registry, Duration::from_secs(3600));
+                key
+            })
+            .collect())
+    }
+
+    /// Get the registry of the authority that created it in a namespace, from
+    /// the cache if it has been fetched recently.
+    pub async fn get_registry(&self, authority: &Pubkey, namespace: &str) -> Option<Registry> {
+        let key = (*authority, namespace.to_string());
         let registry = {
             let reader = self.cache.read().unwrap();
-            reader.get(authority).cloned()
+            reader.get(&key).cloned()
         };
         match registry {
             Some(registry) => Some(registry),
             None => {
-                let Ok(registry) = Registry::fetch(&*self.rpc, authority).await else {
+                let Ok(registry) = Registry::fetch(&*self.rpc, authority, namespace).await else {
                     return None;
                 };
                 let mut writer = self.cache.write().unwrap();
-                writer.insert(*authority, registry, Duration::from_secs(3600));
-                writer.get(authority).cloned()
+                writer.insert(key.clone(), registry, Duration::from_secs(3600));
+                writer.get(&key).cloned()
             }
         }
     }
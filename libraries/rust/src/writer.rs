@@ -2,17 +2,21 @@
 
 use std::{collections::HashSet, sync::Arc};
 
-use anchor_lang::{prelude::Pubkey, AccountDeserialize};
-use lookup_table_registry::{RegistryAccount, RegistryEntry};
+use anchor_lang::prelude::Pubkey;
+use lookup_table_registry::{
+    discriminator::{EMPTY, FROZEN},
+    registry_migration_len, Decoded, NewLookupTable, RegistryAccount, RegistryEntry, RegistryPage,
+    MAX_REGISTRY_ENTRIES,
+};
 use solana_address_lookup_table_program_gateway::state::AddressLookupTable;
 use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcSendTransactionConfig};
 use solana_sdk::{
     account::ReadableAccount, address_lookup_table_account::AddressLookupTableAccount,
-    commitment_config::CommitmentConfig, instruction::Instruction, signature::Signature,
-    signer::Signer, transaction::Transaction,
+    commitment_config::CommitmentConfig, entrypoint::MAX_PERMITTED_DATA_INCREASE,
+    instruction::Instruction, signature::Signature, signer::Signer, transaction::Transaction,
 };
 
-use crate::common::{LookupRegistryError, LookupRegistryResult};
+use crate::common::{get_multiple_accounts_chunked, LookupRegistryError, LookupRegistryResult};
 use crate::instructions::InstructionBuilder;
 
 /// A writer client that creates and updates a registry
@@ -34,6 +38,22 @@ impl LookupRegistryWriter {
         }
     }
 
+    /// Use the registry whose address is derived from the seed authority, which
+    /// is needed once the authority of the registry has been transferred, or
+    /// when the writer's authority is a delegate.
+    pub fn with_seed_authority(mut self, seed_authority: Pubkey) -> Self {
+        self.builder = self.builder.with_seed_authority(seed_authority);
+        self.registry_address = self.builder.registry_address();
+        self
+    }
+
+    /// Use the registry of the seed authority in a namespace
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.builder = self.builder.with_namespace(namespace);
+        self.registry_address = self.builder.registry_address();
+        self
+    }
+
     /// Create a new empty lookup registry
     pub async fn new_or_create(
         rpc: &Arc<RpcClient>,
@@ -41,7 +61,18 @@ impl LookupRegistryWriter {
         payer: Pubkey,
         signer: &dyn Signer,
     ) -> LookupRegistryResult<Self> {
-        let builder = InstructionBuilder::new(authority, payer);
+        Self::new_or_create_in_namespace(rpc, authority, "", payer, signer).await
+    }
+
+    /// Create a new empty lookup registry in a namespace
+    pub async fn new_or_create_in_namespace(
+        rpc: &Arc<RpcClient>,
+        authority: Pubkey,
+        namespace: &str,
+        payer: Pubkey,
+        signer: &dyn Signer,
+    ) -> LookupRegistryResult<Self> {
+        let builder = InstructionBuilder::new(authority, payer).with_namespace(namespace);
         let create_ix = builder.init_registry();
 
         // Check if a registry exists, and create it if it does not.
@@ -80,23 +111,76 @@ impl LookupRegistryWriter {
     ///
     /// Errors:
     /// - Registry has not been created
-    pub async fn get_registry(&self) -> LookupRegistryResult<RegistryAccount> {
+    pub async fn get_registry(&self) -> LookupRegistryResult<Decoded<RegistryAccount>> {
         let account = self.rpc.get_account(&self.registry_address).await?;
-        let registry_account = RegistryAccount::try_deserialize(&mut account.data())?;
+        let registry_account = RegistryAccount::try_deserialize_any_version(account.data())?;
         Ok(registry_account)
     }
 
-    /// Find lookup table addresses in the registry by a discriminator
+    /// Get the overflow pages of the registry account, in order.
+    pub async fn get_registry_pages(
+        &self,
+        registry: &RegistryAccount,
+    ) -> LookupRegistryResult<Vec<Decoded<RegistryPage>>> {
+        let addresses = (1..=registry.pages)
+            .map(|page| self.builder.registry_page_address(page))
+            .collect::<Vec<_>>();
+        if addresses.is_empty() {
+            return Ok(vec![]);
+        }
+        let accounts = get_multiple_accounts_chunked(self.rpc.as_ref(), &addresses).await?;
+        let mut pages = Vec::with_capacity(accounts.len());
+        for (address, account) in addresses.iter().zip(accounts) {
+            let Some(account) = account else {
+                return Err(LookupRegistryError::RegistryNotFound(*address));
+            };
+            pages.push(RegistryPage::try_deserialize_with_tables(account.data())?);
+        }
+        Ok(pages)
+    }
+
+    /// Get the entries of the registry account and all its pages, together
+    /// with the page that stores each entry.
+    async fn get_entries(&self) -> LookupRegistryResult<Vec<(u8, RegistryEntry)>> {
+        let registry = self.get_registry().await?;
+        let pages = self.get_registry_pages(&registry).await?;
+        Ok(registry
+            .tables
+            .into_iter()
+            .map(|entry| (0, entry))
+            .chain(pages.into_iter().flat_map(|page| {
+                let number = page.page;
+                page.tables.into_iter().map(move |entry| (number, entry))
+            }))
+            .collect())
+    }
+
+    /// Find the page that stores a lookup table
+    async fn find_entry(&self, lookup_table: Pubkey) -> LookupRegistryResult<(u8, RegistryEntry)> {
+        self.get_entries()
+            .await?
+            .into_iter()
+            .find(|(_, entry)| entry.table == lookup_table)
+            .ok_or_else(|| {
+                LookupRegistryError::InvalidArgument(
+                    "Registry account does not own the lookup account".to_string(),
+                )
+            })
+    }
+
+    /// Find lookup table addresses in the registry by a discriminator.
+    ///
+    /// Frozen lookup tables are matched by the discriminator they were created with.
     pub async fn find_lookup_table_addresses(
         &self,
         discriminator: u64,
     ) -> LookupRegistryResult<Vec<Pubkey>> {
-        let registry = self.get_registry().await?;
-        let addresses = registry
-            .tables
+        let addresses = self
+            .get_entries()
+            .await?
             .iter()
-            .filter_map(|table| {
-                if table.discriminator == discriminator {
+            .filter_map(|(_, table)| {
+                if table.discriminator & !FROZEN == discriminator {
                     Some(table.table)
                 } else {
                     None
@@ -111,24 +195,26 @@ impl LookupRegistryWriter {
         &self,
         lookup_table: Pubkey,
     ) -> LookupRegistryResult<(RegistryEntry, AddressLookupTableAccount)> {
-        // Get the reigstry and lookup table
-        let accounts = self
-            .rpc
-            .get_multiple_accounts(&[self.registry_address, lookup_table])
-            .await?;
-        // Elide bound checks
-        assert_eq!(accounts.len(), 2);
-        let (Some(registry_account), Some(lookup_table_account)) = (&accounts[0], &accounts[1]) else {
-            return Err(LookupRegistryError::InvalidArgument("Registry account or lookup table not found".to_string()));
-        };
-        let registry_account = RegistryAccount::try_deserialize(&mut registry_account.data())?;
+        let (_, entry, table) = self.find_lookup_table(lookup_table).await?;
+        Ok((entry, table))
+    }
+
+    /// Get a single lookup table in the registry, with the page that stores it
+    async fn find_lookup_table(
+        &self,
+        lookup_table: Pubkey,
+    ) -> LookupRegistryResult<(u8, RegistryEntry, AddressLookupTableAccount)> {
         // Check if the registry has the lookup table, otherwise it doesn't own it
-        let Some(registry_entry) = registry_account
-            .tables
-            .iter()
-            .find(|table| table.table == lookup_table)
-         else {
-            return Err(LookupRegistryError::InvalidArgument("Registry account does not own the lookup account".to_string()));
+        let (page, registry_entry) = self.find_entry(lookup_table).await?;
+        let Some(lookup_table_account) = self
+            .rpc
+            .get_account_with_commitment(&lookup_table, self.rpc.commitment())
+            .await?
+            .value
+        else {
+            return Err(LookupRegistryError::InvalidArgument(
+                "Lookup table not found".to_string(),
+            ));
         };
         // Now deserialize the lookup table
         let table = {
@@ -139,29 +225,325 @@ impl LookupRegistryWriter {
                 addresses: table.addresses.to_vec(),
             }
         };
-        Ok((registry_entry.clone(), table))
+        Ok((page, registry_entry, table))
     }
 
-    /// Create a new lookup table in the registry
+    /// Create a new lookup table in the registry.
+    ///
+    /// The lookup table is stored in the first page with space for it,
+    /// adding a page to the registry if all are full.
     pub async fn create_lookup_table(
         &self,
         payer: Option<&Pubkey>,
         signer: &dyn Signer,
         discriminator: u64,
     ) -> LookupRegistryResult<(Pubkey, u64)> {
-        // Introduce a small delay to prevent slot conflicts
-        tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-        let recent_slot = self.rpc.get_slot().await?;
-        let (ix, table) = self.builder.create_lookup_table(recent_slot, discriminator);
+        let registry = self.get_registry().await?;
+        let mut instructions = vec![];
+        let page = self
+            .page_with_space(&registry, 1, &mut instructions)
+            .await?;
 
-        self.send_transaction(&[ix], payer, signer).await?;
+        let recent_slot = self.next_recent_slot(registry.last_created_slot).await?;
+        let (ix, table) = self
+            .builder
+            .create_lookup_table(recent_slot, discriminator, page);
+        instructions.push(ix);
+
+        self.send_transaction(&instructions, payer, signer).await?;
 
         Ok((table, recent_slot))
     }
 
+    /// Create several lookup tables in the registry with one instruction,
+    /// returning the lookup tables with the recent slots used to create them.
+    ///
+    /// The lookup tables are stored in the first page with space for all of them.
+    pub async fn create_lookup_tables(
+        &self,
+        payer: Option<&Pubkey>,
+        signer: &dyn Signer,
+        discriminators: &[u64],
+    ) -> LookupRegistryResult<Vec<(Pubkey, u64)>> {
+        let registry = self.get_registry().await?;
+        let mut instructions = vec![];
+        let page = self
+            .page_with_space(&registry, discriminators.len(), &mut instructions)
+            .await?;
+
+        let recent_slots = self
+            .next_recent_slots(registry.last_created_slot, discriminators.len())
+            .await?;
+        let tables = recent_slots
+            .iter()
+            .zip(discriminators)
+            .map(|(recent_slot, discriminator)| NewLookupTable {
+                recent_slot: *recent_slot,
+                discriminator: *discriminator,
+            })
+            .collect::<Vec<_>>();
+        let (ix, lookup_tables) = self.builder.create_lookup_tables(&tables, page);
+        instructions.push(ix);
+
+        self.send_transaction(&instructions, payer, signer).await?;
+
+        Ok(lookup_tables.into_iter().zip(recent_slots).collect())
+    }
+
+    /// Import a lookup table that was created outside of the registry.
+    ///
+    /// The authority of the lookup table must be the registry account or the
+    /// registry authority. The lookup table is stored in the first page with
+    /// space for it.
+    pub async fn import_lookup_table(
+        &self,
+        lookup_table: Pubkey,
+        discriminator: u64,
+        payer: Option<&Pubkey>,
+        signer: &dyn Signer,
+    ) -> LookupRegistryResult<()> {
+        if self.find_entry(lookup_table).await.is_ok() {
+            return Err(LookupRegistryError::InvalidArgument(
+                "The lookup table is already in the registry".to_string(),
+            ));
+        }
+        let registry = self.get_registry().await?;
+        let mut instructions = vec![];
+        let page = self
+            .page_with_space(&registry, 1, &mut instructions)
+            .await?;
+        instructions.push(
+            self.builder
+                .import_lookup_table(lookup_table, discriminator, page),
+        );
+
+        self.send_transaction(&instructions, payer, signer).await?;
+
+        Ok(())
+    }
+
+    /// Find the first page of the registry with space for a number of entries,
+    /// where page 0 is the registry account. If all are full, an instruction to
+    /// add a page is pushed to the instructions.
+    async fn page_with_space(
+        &self,
+        registry: &RegistryAccount,
+        entries: usize,
+        instructions: &mut Vec<Instruction>,
+    ) -> LookupRegistryResult<u8> {
+        if entries > MAX_REGISTRY_ENTRIES {
+            return Err(LookupRegistryError::InvalidArgument(format!(
+                "Cannot add more than {MAX_REGISTRY_ENTRIES} entries to a page"
+            )));
+        }
+        if registry.len as usize + entries <= MAX_REGISTRY_ENTRIES {
+            return Ok(0);
+        }
+        let pages = self.get_registry_pages(registry).await?;
+        match pages
+            .iter()
+            .find(|page| page.len as usize + entries <= MAX_REGISTRY_ENTRIES)
+        {
+            Some(page) => Ok(page.page),
+            None => {
+                let page = registry.pages.checked_add(1).ok_or_else(|| {
+                    LookupRegistryError::GeneralError("The registry is full".to_string())
+                })?;
+                instructions.push(self.builder.add_registry_page(page));
+                Ok(page)
+            }
+        }
+    }
+
+    /// Propose a new authority for the registry. The transfer takes effect once
+    /// the new authority accepts it.
+    pub async fn propose_authority(
+        &self,
+        new_authority: Pubkey,
+        payer: Option<&Pubkey>,
+        signer: &dyn Signer,
+    ) -> LookupRegistryResult<()> {
+        let ix = self.builder.propose_authority(new_authority);
+
+        self.send_transaction(&[ix], payer, signer).await?;
+
+        Ok(())
+    }
+
+    /// Accept the authority of the registry as its proposed authority.
+    ///
+    /// The writer's authority must be the proposed authority, and its seed
+    /// authority the registry's current seed authority.
+    pub async fn accept_authority(
+        &self,
+        payer: Option<&Pubkey>,
+        signer: &dyn Signer,
+    ) -> LookupRegistryResult<()> {
+        let ix = self.builder.accept_authority();
+
+        self.send_transaction(&[ix], payer, signer).await?;
+
+        Ok(())
+    }
+
+    /// Add a delegate that can sign for the authority with the given
+    /// permissions, see [lookup_table_registry::permissions].
+    pub async fn add_delegate(
+        &self,
+        delegate: Pubkey,
+        permissions: u8,
+        payer: Option<&Pubkey>,
+        signer: &dyn Signer,
+    ) -> LookupRegistryResult<()> {
+        let ix = self.builder.add_delegate(delegate, permissions);
+
+        self.send_transaction(&[ix], payer, signer).await?;
+
+        Ok(())
+    }
+
+    /// Revoke a delegate of the authority.
+    pub async fn revoke_delegate(
+        &self,
+        delegate: Pubkey,
+        payer: Option<&Pubkey>,
+        signer: &dyn Signer,
+    ) -> LookupRegistryResult<()> {
+        let ix = self.builder.revoke_delegate(delegate);
+
+        self.send_transaction(&[ix], payer, signer).await?;
+
+        Ok(())
+    }
+
+    /// Migrate the registry account to the current layout, with as many
+    /// instructions as it needs to grow to the current layout. Registries that
+    /// have the current version are left as they are.
+    ///
+    /// Pages are only created with the current layout, so they are never migrated.
+    pub async fn migrate_registry(
+        &self,
+        payer: Option<&Pubkey>,
+        signer: &dyn Signer,
+    ) -> LookupRegistryResult<()> {
+        let account = self.rpc.get_account(&self.registry_address).await?;
+        if let Some(migrated_len) = registry_migration_len(account.data())? {
+            let instructions = vec![
+                self.builder.migrate_registry();
+                migration_steps(account.data().len(), migrated_len)
+            ];
+            self.send_transaction(&instructions, payer, signer).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Close the registry and all its pages, sending their rent to the payer of
+    /// the writer. All lookup tables in the registry must have been closed.
+    ///
+    /// The pages are closed from the last one in transactions of at most
+    /// [CLOSE_PAGES_PER_TRANSACTION] pages, and the registry account in a last
+    /// transaction.
+    pub async fn close_registry(
+        &self,
+        payer: Option<&Pubkey>,
+        signer: &dyn Signer,
+    ) -> LookupRegistryResult<()> {
+        let registry = self.get_registry().await?;
+        for instructions in close_registry_transactions(&self.builder, registry.pages) {
+            self.send_transaction(&instructions, payer, signer).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Compact the registry account and each of its pages that have more than
+    /// `max_empty` empty entries, sending the rent that is no longer needed to
+    /// the registry authority.
+    pub async fn compact_registry(
+        &self,
+        max_empty: usize,
+        payer: Option<&Pubkey>,
+        signer: &dyn Signer,
+    ) -> LookupRegistryResult<()> {
+        let registry = self.get_registry().await?;
+        let pages = self.get_registry_pages(&registry).await?;
+        let instructions = std::iter::once((0, empty_entries(&registry.tables)))
+            .chain(
+                pages
+                    .iter()
+                    .map(|page| (page.page, empty_entries(&page.tables))),
+            )
+            .filter(|(_, empty)| *empty > max_empty)
+            .map(|(page, _)| self.builder.compact_registry(page, registry.authority))
+            .collect::<Vec<_>>();
+        // Keep the transactions within the size limit for registries with many pages
+        for instructions in instructions.chunks(8) {
+            self.send_transaction(instructions, payer, signer).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Get a recent slot that is later than the last slot used to create a
+    /// lookup table in the registry, waiting for the cluster to reach one.
+    async fn next_recent_slot(&self, last_created_slot: u64) -> LookupRegistryResult<u64> {
+        loop {
+            let slot = self.rpc.get_slot().await?;
+            if slot > last_created_slot {
+                return Ok(slot);
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+        }
+    }
+
+    /// Get distinct recent slots that are later than the last slot used to
+    /// create a lookup table in the registry, waiting for the cluster to produce
+    /// enough of them.
+    ///
+    /// Only slots with a block are used, as the lookup table program requires
+    /// the slot to be in the slot hashes.
+    async fn next_recent_slots(
+        &self,
+        last_created_slot: u64,
+        count: usize,
+    ) -> LookupRegistryResult<Vec<u64>> {
+        loop {
+            let slot = self
+                .rpc
+                .get_slot_with_commitment(CommitmentConfig::confirmed())
+                .await?;
+            // Keep well within the slots in the slot hashes
+            let start = (last_created_slot + 1).max(slot.saturating_sub(150));
+            if slot >= start {
+                let blocks = self
+                    .rpc
+                    .get_blocks_with_commitment(start, Some(slot), CommitmentConfig::confirmed())
+                    .await?;
+                if blocks.len() >= count {
+                    return Ok(blocks[blocks.len() - count..].to_vec());
+                }
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+        }
+    }
+
+    /// The earliest slot when a deactivated lookup table can be closed with
+    /// [Self::remove_lookup_table], or none if it is not deactivated.
+    ///
+    /// Closing the lookup table can still fail after this slot if slots were
+    /// skipped, see [RegistryEntry::closeable_at_slot].
+    pub async fn closeable_at_slot(
+        &self,
+        lookup_table: Pubkey,
+    ) -> LookupRegistryResult<Option<u64>> {
+        let (_, entry) = self.find_entry(lookup_table).await?;
+        Ok(entry.closeable_at_slot())
+    }
+
     /// Removes a lookup table by either deactivating or closing it.
     /// Lookup tables cannot be closed while active, and require deactivating for
-    /// a number of slots before being closed.
+    /// a number of slots before being closed, see [Self::closeable_at_slot].
     ///
     /// Callers can invoke this function twice to close a lookup table.
     pub async fn remove_lookup_table(
@@ -170,22 +552,103 @@ impl LookupRegistryWriter {
         payer: Option<&Pubkey>,
         signer: &dyn Signer,
     ) -> LookupRegistryResult<()> {
-        let ix = self.builder.remove_lookup_table(lookup_table);
+        let (page, _) = self.find_entry(lookup_table).await?;
+        let ix = self.builder.remove_lookup_table(lookup_table, page);
+
+        self.send_transaction(&[ix], payer, signer).await?;
+
+        Ok(())
+    }
+
+    /// Freezes a lookup table, after which it can no longer be extended or removed.
+    pub async fn freeze_lookup_table(
+        &self,
+        lookup_table: Pubkey,
+        payer: Option<&Pubkey>,
+        signer: &dyn Signer,
+    ) -> LookupRegistryResult<()> {
+        let (page, _) = self.find_entry(lookup_table).await?;
+        let ix = self.builder.freeze_lookup_table(lookup_table, page);
 
         self.send_transaction(&[ix], payer, signer).await?;
 
         Ok(())
     }
 
+    /// Set the recipient of a lookup table's lamports, so that anyone can
+    /// close it once it has been deactivated and has cooled down, or clear it
+    /// so that only the authority can close it. The registry account must be
+    /// the authority of the lookup table.
+    pub async fn set_auto_close(
+        &self,
+        lookup_table: Pubkey,
+        close_recipient: Option<Pubkey>,
+        payer: Option<&Pubkey>,
+        signer: &dyn Signer,
+    ) -> LookupRegistryResult<()> {
+        let (page, _) = self.find_entry(lookup_table).await?;
+        let ix = self
+            .builder
+            .set_auto_close(lookup_table, close_recipient, page);
+
+        self.send_transaction(&[ix], payer, signer).await?;
+
+        Ok(())
+    }
+
+    /// Remove the entry of a lookup table that was closed outside the registry,
+    /// see [crate::common::Registry::stale]
+    pub async fn prune_entry(
+        &self,
+        lookup_table: Pubkey,
+        payer: Option<&Pubkey>,
+        signer: &dyn Signer,
+    ) -> LookupRegistryResult<()> {
+        let (page, _) = self.find_entry(lookup_table).await?;
+        let ix = crate::instructions::prune_entry(
+            self.registry_address,
+            (page > 0).then(|| self.builder.registry_page_address(page)),
+            lookup_table,
+        );
+
+        self.send_transaction(&[ix], payer, signer).await?;
+
+        Ok(())
+    }
+
+    /// Set the label of a lookup table, and the program that it is used with.
+    pub async fn set_entry_metadata(
+        &self,
+        lookup_table: Pubkey,
+        label: &str,
+        program: Pubkey,
+        payer: Option<&Pubkey>,
+        signer: &dyn Signer,
+    ) -> LookupRegistryResult<()> {
+        let (page, _) = self.find_entry(lookup_table).await?;
+        let ix = self
+            .builder
+            .set_entry_metadata(lookup_table, label, program, page);
+
+        self.send_transaction(&[ix], payer, signer).await?;
+
+        Ok(())
+    }
+
+    /// Append addresses that are not in a lookup table yet to it.
+    ///
+    /// The program rejects the append if the discriminator of the lookup table
+    /// in the registry is not the expected discriminator.
     // TODO: can return the remaining space, or all the accounts that exist
     pub async fn append_to_lookup_table(
         &self,
         lookup_table: Pubkey,
         addresses: &[Pubkey],
+        expected_discriminator: u64,
         payer: Option<&Pubkey>,
         signer: &dyn Signer,
     ) -> LookupRegistryResult<()> {
-        let (entry, table) = self.get_lookup_table(lookup_table).await?;
+        let (page, _, table) = self.find_lookup_table(lookup_table).await?;
         let distinct_addresses = addresses
             .iter()
             .filter(|input| !table.addresses.contains(input))
@@ -196,7 +659,9 @@ impl LookupRegistryWriter {
         let ix = self.builder.append_to_lookup_table(
             lookup_table,
             &distinct_addresses[..],
-            entry.discriminator,
+            expected_discriminator,
+            true,
+            page,
         );
 
         self.send_transaction(&[ix], payer, signer).await?;
@@ -228,14 +693,72 @@ impl LookupRegistryWriter {
     }
 }
 
+/// The most pages that are closed in one transaction
+pub const CLOSE_PAGES_PER_TRANSACTION: usize = 8;
+
+/// The instructions of each transaction that closes a registry with its pages
+fn close_registry_transactions(builder: &InstructionBuilder, pages: u8) -> Vec<Vec<Instruction>> {
+    let page_instructions = (1..=pages)
+        .rev()
+        .map(|page| builder.close_registry_page(page))
+        .collect::<Vec<_>>();
+    let mut transactions = page_instructions
+        .chunks(CLOSE_PAGES_PER_TRANSACTION)
+        .map(|chunk| chunk.to_vec())
+        .collect::<Vec<_>>();
+    transactions.push(vec![builder.close_registry()]);
+    transactions
+}
+
+/// The number of migration instructions that an account needs to grow to its
+/// migrated length, and to be migrated
+fn migration_steps(len: usize, migrated_len: usize) -> usize {
+    let growth = migrated_len.saturating_sub(len);
+    ((growth + MAX_PERMITTED_DATA_INCREASE - 1) / MAX_PERMITTED_DATA_INCREASE).max(1)
+}
+
+/// The number of empty entries left behind by closed lookup tables
+fn empty_entries(tables: &[RegistryEntry]) -> usize {
+    tables
+        .iter()
+        .filter(|entry| entry.discriminator == EMPTY)
+        .count()
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
 
-    use solana_sdk::{commitment_config::CommitmentConfig, signature::Keypair, signer::Signer};
+    use lookup_table_registry::ErrorCode;
+    use solana_sdk::{
+        commitment_config::CommitmentConfig, instruction::InstructionError, signature::Keypair,
+        signer::Signer, transaction::TransactionError,
+    };
 
     use super::*;
 
+    #[test]
+    fn close_registry_in_chunks_of_pages() {
+        let builder = InstructionBuilder::new(Pubkey::new_unique(), Pubkey::new_unique());
+        let transactions = close_registry_transactions(&builder, 17);
+        let sizes = transactions.iter().map(Vec::len).collect::<Vec<_>>();
+        assert_eq!(sizes, vec![8, 8, 1, 1]);
+        assert_eq!(transactions[0][0], builder.close_registry_page(17));
+        assert_eq!(transactions[2][0], builder.close_registry_page(1));
+        assert_eq!(transactions[3][0], builder.close_registry());
+
+        let transactions = close_registry_transactions(&builder, 0);
+        assert_eq!(transactions, vec![vec![builder.close_registry()]]);
+    }
+
+    #[test]
+    fn migrate_registry_in_steps_of_max_data_increase() {
+        assert_eq!(migration_steps(1000, 1000), 1);
+        assert_eq!(migration_steps(1000, 1001), 1);
+        assert_eq!(migration_steps(0, MAX_PERMITTED_DATA_INCREASE), 1);
+        assert_eq!(migration_steps(0, MAX_PERMITTED_DATA_INCREASE + 1), 2);
+    }
+
     #[tokio::test]
     #[allow(clippy::result_large_err)]
     #[ignore = "this test takes over 5 minutes. run it with './check full' or 'cargo test -- --include-ignored'"]
@@ -265,7 +788,7 @@ mod tests {
             addresses.push(Keypair::new().pubkey());
         });
         registry
-            .append_to_lookup_table(lookup_table, &addresses, None, &authority_keypair)
+            .append_to_lookup_table(lookup_table, &addresses, 2, None, &authority_keypair)
             .await?;
 
         // Get the lookup table, it should have 12 entries
@@ -275,23 +798,44 @@ mod tests {
         assert_eq!(entry.table, lookup_table);
         assert_eq!(table.key, lookup_table);
 
-        // Create another lookup table, then close the first one
+        // Create another lookup table with a different discriminator, then close the first one
         let (lookup_table2, _) = registry
-            .create_lookup_table(None, &authority_keypair, 2)
+            .create_lookup_table(None, &authority_keypair, 3)
             .await?;
+        assert_eq!(
+            registry.find_lookup_table_addresses(2).await?,
+            vec![lookup_table]
+        );
+        assert_eq!(
+            registry.find_lookup_table_addresses(3).await?,
+            vec![lookup_table2]
+        );
 
         // Deactivate the lookup table
         registry
             .remove_lookup_table(lookup_table, Some(&authority), &authority_keypair)
             .await?;
         // Trying to close it immediately after deactivating should fail
-        registry
+        let error = registry
             .remove_lookup_table(lookup_table, Some(&authority), &authority_keypair)
             .await
             .unwrap_err();
+        let LookupRegistryError::ClientError(error) = error else {
+            panic!("unexpected error {error:?}");
+        };
+        assert_eq!(
+            error.get_transaction_error(),
+            Some(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(ErrorCode::TableStillCoolingDown.into())
+            ))
+        );
 
-        // Wait a while for the table to be closeable
-        tokio::time::sleep(Duration::from_secs(240)).await;
+        // Wait for the table to be closeable
+        let closeable_at_slot = registry.closeable_at_slot(lookup_table).await?.unwrap();
+        while rpc.get_slot().await? < closeable_at_slot {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        }
         registry
             .remove_lookup_table(lookup_table, Some(&authority), &authority_keypair)
             .await?;
@@ -301,10 +845,124 @@ mod tests {
         assert_eq!(registry_account.len, 1);
         assert_eq!(registry_account.capacity, 2);
         assert_eq!(registry_account.tables.len(), 2);
-        assert_eq!(registry_account.tables.get(0).unwrap().discriminator, 0);
-        assert_eq!(registry_account.tables.get(1).unwrap().discriminator, 2);
+        assert_eq!(registry_account.tables.first().unwrap().discriminator, 0);
+        assert_eq!(registry_account.tables.get(1).unwrap().discriminator, 3);
         assert_eq!(registry_account.tables.get(1).unwrap().table, lookup_table2);
 
         Ok(())
     }
+
+    #[tokio::test]
+    #[allow(clippy::result_large_err)]
+    #[ignore = "this test needs a local validator. run it with './check full' or 'cargo test -- --include-ignored'"]
+    async fn test_transfer_authority() -> LookupRegistryResult<()> {
+        let old_keypair = Keypair::new();
+        let old_authority = old_keypair.pubkey();
+        let new_keypair = Keypair::new();
+        let new_authority = new_keypair.pubkey();
+
+        let rpc = Arc::new(RpcClient::new_with_commitment(
+            "http://localhost:8899".to_string(),
+            CommitmentConfig::processed(),
+        ));
+        rpc.request_airdrop(&old_authority, 1_000_000_000).await?;
+        rpc.request_airdrop(&new_authority, 1_000_000_000).await?;
+
+        let registry =
+            LookupRegistryWriter::new_or_create(&rpc, old_authority, old_authority, &old_keypair)
+                .await?;
+        let (lookup_table, _) = registry.create_lookup_table(None, &old_keypair, 2).await?;
+
+        // Transfer the registry to the new authority
+        registry
+            .propose_authority(new_authority, Some(&old_authority), &old_keypair)
+            .await?;
+        let registry = LookupRegistryWriter::new(&rpc, new_authority, new_authority)
+            .with_seed_authority(old_authority);
+        registry
+            .accept_authority(Some(&new_authority), &new_keypair)
+            .await?;
+        assert_eq!(registry.get_registry().await?.authority, new_authority);
+
+        // The lookup table created before the transfer is changed without the old key
+        let addresses = (0..3).map(|_| Keypair::new().pubkey()).collect::<Vec<_>>();
+        registry
+            .append_to_lookup_table(
+                lookup_table,
+                &addresses,
+                2,
+                Some(&new_authority),
+                &new_keypair,
+            )
+            .await?;
+        let (_, table) = registry.get_lookup_table(lookup_table).await?;
+        assert_eq!(table.addresses.len(), 3);
+
+        registry
+            .remove_lookup_table(lookup_table, Some(&new_authority), &new_keypair)
+            .await?;
+        let (entry, _) = registry.get_lookup_table(lookup_table).await?;
+        assert_eq!(
+            entry.discriminator,
+            lookup_table_registry::discriminator::DEACTIVATED
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[allow(clippy::result_large_err)]
+    #[ignore = "this test needs a local validator. run it with './check full' or 'cargo test -- --include-ignored'"]
+    async fn test_delegate_appends() -> LookupRegistryResult<()> {
+        let authority_keypair = Keypair::new();
+        let authority = authority_keypair.pubkey();
+        let delegate_keypair = Keypair::new();
+        let delegate = delegate_keypair.pubkey();
+
+        let rpc = Arc::new(RpcClient::new_with_commitment(
+            "http://localhost:8899".to_string(),
+            CommitmentConfig::processed(),
+        ));
+        rpc.request_airdrop(&authority, 1_000_000_000).await?;
+        rpc.request_airdrop(&delegate, 1_000_000_000).await?;
+
+        let registry =
+            LookupRegistryWriter::new_or_create(&rpc, authority, authority, &authority_keypair)
+                .await?;
+        let (lookup_table, _) = registry
+            .create_lookup_table(None, &authority_keypair, 2)
+            .await?;
+        registry
+            .add_delegate(
+                delegate,
+                lookup_table_registry::permissions::APPEND,
+                Some(&authority),
+                &authority_keypair,
+            )
+            .await?;
+
+        // The delegate extends the lookup table, which the authority doesn't sign for
+        let delegated =
+            LookupRegistryWriter::new(&rpc, delegate, delegate).with_seed_authority(authority);
+        let addresses = (0..3).map(|_| Keypair::new().pubkey()).collect::<Vec<_>>();
+        delegated
+            .append_to_lookup_table(
+                lookup_table,
+                &addresses,
+                2,
+                Some(&delegate),
+                &delegate_keypair,
+            )
+            .await?;
+        let (_, table) = registry.get_lookup_table(lookup_table).await?;
+        assert_eq!(table.addresses, addresses);
+
+        // The delegate can't remove the lookup table without the permission
+        delegated
+            .remove_lookup_table(lookup_table, Some(&delegate), &delegate_keypair)
+            .await
+            .unwrap_err();
+
+        Ok(())
+    }
 }
@@ -81,6 +81,20 @@ fn create_lookup_table_common(
     (instruction, lookup_table_address)
 }
 
+/// Constructs an instruction that freezes an address lookup
+/// table so that it can never be closed or extended again. Empty
+/// lookup tables cannot be frozen.
+pub fn freeze_lookup_table(lookup_table_address: Pubkey, authority_address: Pubkey) -> Instruction {
+    Instruction::new_with_bincode(
+        id(),
+        &ProgramInstruction::FreezeLookupTable,
+        vec![
+            AccountMeta::new(lookup_table_address, false),
+            AccountMeta::new_readonly(authority_address, true),
+        ],
+    )
+}
+
 /// Constructs an instruction which extends an address lookup
 /// table account with new addresses.
 pub fn extend_lookup_table(
@@ -110,18 +124,13 @@ pub fn extend_lookup_table(
 
 #[derive(Serialize)]
 enum ProgramInstruction {
-    CreateLookupTable {
-        recent_slot: Slot,
-        bump_seed: u8,
-    },
-    #[allow(dead_code)]
+    CreateLookupTable { recent_slot: Slot, bump_seed: u8 },
     FreezeLookupTable,
-    ExtendLookupTable {
-        new_addresses: Vec<Pubkey>,
-    },
+    ExtendLookupTable { new_addresses: Vec<Pubkey> },
 }
 
 #[cfg(test)]
+#[allow(clippy::identity_op, clippy::needless_range_loop)]
 mod test {
     use solana_address_lookup_table_program::instruction as real;
 
@@ -160,6 +169,18 @@ mod test {
         }
     }
 
+    #[test]
+    fn freeze_lookup_table() {
+        let addr = addresses();
+        for i in 0..6 {
+            let n = i * 2;
+            assert_eq!(
+                real::freeze_lookup_table(addr[n], addr[1 + n]),
+                super::freeze_lookup_table(addr[n], addr[1 + n]),
+            );
+        }
+    }
+
     #[test]
     fn create_lookup_table() {
         let addr = addresses();
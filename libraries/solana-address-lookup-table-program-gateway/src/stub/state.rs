@@ -60,6 +60,7 @@ impl<'a> AddressLookupTable<'a> {
 }
 
 #[cfg(test)]
+#[allow(clippy::identity_op, clippy::needless_range_loop)]
 mod test {
     use std::borrow::Cow;
 
@@ -0,0 +1,73 @@
+//! Events emitted by the program when a registry or its lookup tables change,
+//! so that indexers can follow changes without comparing registry accounts.
+
+use anchor_lang::prelude::*;
+
+/// A registry account has been initialized
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryInitialized {
+    /// The registry account
+    pub registry: Pubkey,
+    /// The authority of the registry
+    pub authority: Pubkey,
+    /// The namespace of the registry, empty if it has none
+    pub namespace: String,
+}
+
+/// A lookup table has been created in a registry
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableCreated {
+    /// The registry account that the lookup table belongs to
+    pub registry: Pubkey,
+    /// The lookup table
+    pub table: Pubkey,
+    /// The recent slot that the lookup table address was derived from
+    pub slot: u64,
+    /// The discriminator of the lookup table
+    pub discriminator: u64,
+}
+
+/// Addresses have been appended to a lookup table
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableExtended {
+    /// The registry account that the lookup table belongs to
+    pub registry: Pubkey,
+    /// The lookup table
+    pub table: Pubkey,
+    /// The number of addresses appended
+    pub count: u16,
+}
+
+/// A lookup table has been deactivated, and can be closed once it has cooled down
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableDeactivated {
+    /// The registry account that the lookup table belongs to
+    pub registry: Pubkey,
+    /// The lookup table
+    pub table: Pubkey,
+}
+
+/// A lookup table has been closed and removed from its registry
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableClosed {
+    /// The registry account that the lookup table belonged to
+    pub registry: Pubkey,
+    /// The lookup table
+    pub table: Pubkey,
+}
+
+/// The entry of a lookup table that was closed outside the registry has been
+/// removed from its registry
+#[event]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryPruned {
+    /// The registry account that the lookup table belonged to
+    pub registry: Pubkey,
+    /// The lookup table
+    pub table: Pubkey,
+}
@@ -9,10 +9,10 @@
 //! This program creates a registry that stores the addresses created and thus
 //! can be queried more efficiently.
 //!
-//! Note: Nothing prevents a registry from having duplicate entries as the
-//! address lookup program does not enforce uniqueness.
-//! Thus callers (libraries) should enforce this to prevent wasting lamports
-//! storing addresses that have duplicates.
+//! Note: The address lookup program does not enforce uniqueness of the
+//! addresses in a lookup table. When appending through the registry, addresses
+//! that are already in the lookup table are either skipped or rejected, to
+//! prevent wasting lamports storing addresses that have duplicates.
 //!
 //! Possible use-cases:
 //! - A wallet or margin account can store the ATAs owned by it, or those of common tokens.
@@ -20,13 +20,20 @@
 
 #![allow(clippy::result_large_err, clippy::assertions_on_constants)]
 
+#[cfg(feature = "program")]
+use std::cell::RefMut;
+
 use anchor_lang::prelude::*;
 use solana_address_lookup_table_program_gateway as solana_address_lookup_table_program;
 
 declare_id!("LTR8xXcSrEDsCbTWPY4JmJREFdMz4uYh65uajkVjzru");
 
+pub mod events;
+pub mod migration;
 mod state;
 
+pub use events::*;
+pub use migration::*;
 pub use state::*;
 
 /// Special constants for the discriminator
@@ -35,8 +42,30 @@ pub mod discriminator {
     pub const EMPTY: u64 = 0b0;
     /// The lookup table has been deactivated, and can be closed in a future slot
     pub const DEACTIVATED: u64 = 0b1;
+    /// Flag set on the discriminator of a lookup table that has been frozen.
+    /// The remaining bits keep the discriminator that the table was created with.
+    pub const FROZEN: u64 = 1 << 63;
 
     const _: () = assert!(EMPTY < DEACTIVATED);
+    const _: () = assert!(DEACTIVATED < FROZEN);
+}
+
+/// Permissions that the authority of a registry can grant to a delegate.
+///
+/// Delegates only act on lookup tables that the registry account is the
+/// authority of, which are the lookup tables created by the registry since
+/// version 1. Lookup tables that a key is the authority of can only be changed
+/// by the registry authority, as the delegate can't sign for the key.
+pub mod permissions {
+    /// The delegate can append addresses to lookup tables
+    pub const APPEND: u8 = 1 << 0;
+    /// The delegate can create lookup tables, set their metadata, and add pages
+    /// to the registry
+    pub const CREATE: u8 = 1 << 1;
+    /// The delegate can deactivate and close lookup tables, and compact the registry
+    pub const REMOVE: u8 = 1 << 2;
+    /// All the permissions that can be granted
+    pub const ALL: u8 = APPEND | CREATE | REMOVE;
 }
 
 /// Lookup table registry program stub
@@ -53,11 +82,107 @@ pub mod lookup_table_registry {
         unimplemented!()
     }
 
+    /// Initialize a registry account owned by the authority in a namespace.
+    ///
+    /// Errors if a registry account already exists in the namespace.
+    pub fn init_namespaced_registry_account(
+        ctx: Context<InitNamespacedRegistryAccount>,
+        namespace: String,
+    ) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Add an overflow page to a registry account.
+    pub fn add_registry_page(ctx: Context<AddRegistryPage>) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Migrate a registry account to the current layout.
+    pub fn migrate_registry(ctx: Context<MigrateRegistry>) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Propose a new authority for a registry account.
+    pub fn propose_authority(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Accept the authority of a registry account as the proposed authority.
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Close an empty registry account or its last page.
+    pub fn close_registry_account(ctx: Context<CloseRegistryAccount>) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Remove the empty entries of a registry account or page, and shrink it.
+    pub fn compact_registry(ctx: Context<CompactRegistry>) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Add a delegate to a registry account, or update its permissions.
+    pub fn add_delegate(
+        ctx: Context<UpdateDelegates>,
+        delegate: Pubkey,
+        permissions: u8,
+    ) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Revoke a delegate of a registry account.
+    pub fn revoke_delegate(ctx: Context<UpdateDelegates>, delegate: Pubkey) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Set the signers and threshold of the multisig authority of a registry.
+    pub fn set_multisig(
+        ctx: Context<SetMultisig>,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        unimplemented!()
+    }
+
     /// Create a lookup table in the registry
     pub fn create_lookup_table(
         ctx: Context<CreateLookupTable>,
         recent_slot: u64,
-        _discriminator: u64,
+        discriminator: u64,
+    ) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Create a lookup table in the registry, and extend it with addresses.
+    pub fn create_lookup_table_with_addresses(
+        ctx: Context<CreateLookupTable>,
+        recent_slot: u64,
+        discriminator: u64,
+        addresses: Vec<Pubkey>,
+        skip_duplicates: bool,
+    ) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Create several lookup tables in the registry.
+    pub fn create_lookup_tables<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateLookupTables<'info>>,
+        tables: Vec<NewLookupTable>,
+    ) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Add a lookup table that was created outside the registry to the registry.
+    pub fn import_lookup_table(ctx: Context<ImportLookupTable>, discriminator: u64) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Set the metadata of a lookup table in the registry.
+    pub fn set_entry_metadata(
+        ctx: Context<SetEntryMetadata>,
+        label: String,
+        program: Pubkey,
     ) -> Result<()> {
         unimplemented!()
     }
@@ -66,7 +191,8 @@ pub mod lookup_table_registry {
     pub fn append_to_lookup_table(
         ctx: Context<AppendToLookupTable>,
         addresses: Vec<Pubkey>,
-        _discriminator: u64,
+        discriminator: u64,
+        skip_duplicates: bool,
     ) -> Result<()> {
         unimplemented!()
     }
@@ -76,13 +202,36 @@ pub mod lookup_table_registry {
     pub fn remove_lookup_table(ctx: Context<RemoveLookupTable>) -> Result<()> {
         unimplemented!()
     }
+
+    /// Freeze a lookup table so that it can no longer be modified or removed.
+    pub fn freeze_lookup_table(ctx: Context<FreezeLookupTable>) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Set the recipient of a lookup table's lamports when anyone closes it.
+    pub fn set_auto_close(
+        ctx: Context<SetAutoClose>,
+        close_recipient: Option<Pubkey>,
+    ) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Close a deactivated lookup table that has a close recipient.
+    pub fn close_deactivated_table(ctx: Context<CloseDeactivatedTable>) -> Result<()> {
+        unimplemented!()
+    }
+
+    /// Remove the entry of a lookup table that was closed outside the registry.
+    pub fn prune_entry(ctx: Context<PruneEntry>) -> Result<()> {
+        unimplemented!()
+    }
 }
 
 /// Lookup table registry program
 #[cfg_attr(feature = "program", program)]
 #[cfg(feature = "program")]
 pub mod lookup_table_registry {
-    use solana_program::program::invoke;
+    use solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
 
     use super::*;
 
@@ -90,159 +239,713 @@ pub mod lookup_table_registry {
     ///
     /// Errors if a registry account already exists.
     pub fn init_registry_account(ctx: Context<InitRegistryAccount>) -> Result<()> {
-        let clock = Clock::get()?;
-        let registry = &mut ctx.accounts.registry_account;
-        registry.authority = ctx.accounts.authority.key();
-        registry.version = 0;
-        registry.len = 0;
-        registry.capacity = 0;
-        registry.last_created_slot = clock.slot;
-        registry.seed = [*ctx.bumps.get("registry_account").unwrap()];
-        registry.tables = vec![];
+        init_registry(
+            &mut *ctx.accounts.registry_account.load_init()?,
+            ctx.accounts.authority.key(),
+            &[],
+            *ctx.bumps.get("registry_account").unwrap(),
+        )?;
+
+        emit!(RegistryInitialized {
+            registry: ctx.accounts.registry_account.key(),
+            authority: ctx.accounts.authority.key(),
+            namespace: String::new(),
+        });
 
         Ok(())
     }
 
-    /// Create a lookup table in the registry
-    pub fn create_lookup_table(
-        ctx: Context<CreateLookupTable>,
-        recent_slot: u64,
-        _discriminator: u64,
+    /// Initialize a registry account owned by the authority in a namespace.
+    ///
+    /// The address of the registry account is derived from the authority and
+    /// the namespace, so an authority can have a registry in each namespace.
+    /// An empty namespace is the registry created by [init_registry_account].
+    ///
+    /// Errors if a registry account already exists in the namespace.
+    pub fn init_namespaced_registry_account(
+        ctx: Context<InitNamespacedRegistryAccount>,
+        namespace: String,
     ) -> Result<()> {
-        if ctx.accounts.registry_account.len as usize == MAX_REGISTRY_ENTRIES {
-            return err!(ErrorCode::TooManyEntries);
-        }
-        let discriminator = discriminator::DEACTIVATED + 1;
-        // Discriminator can't be 0
-        if discriminator <= discriminator::DEACTIVATED {
-            return err!(ErrorCode::InvalidDiscriminator);
+        init_registry(
+            &mut *ctx.accounts.registry_account.load_init()?,
+            ctx.accounts.authority.key(),
+            namespace.as_bytes(),
+            *ctx.bumps.get("registry_account").unwrap(),
+        )?;
+
+        emit!(RegistryInitialized {
+            registry: ctx.accounts.registry_account.key(),
+            authority: ctx.accounts.authority.key(),
+            namespace,
+        });
+
+        Ok(())
+    }
+
+    /// Add an overflow page to a registry account.
+    ///
+    /// Pages are used to store entries once the registry account and all its
+    /// existing pages have reached [MAX_REGISTRY_ENTRIES].
+    pub fn add_registry_page(ctx: Context<AddRegistryPage>) -> Result<()> {
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            Some(permissions::CREATE),
+        )?;
+        let mut registry = ctx.accounts.registry_account.load_header_mut()?;
+        registry.pages = registry.next_page()?;
+
+        let mut page = ctx.accounts.registry_page.load_init()?;
+        page.registry = ctx.accounts.registry_account.key();
+        page.page = registry.pages;
+        page.seed = [*ctx.bumps.get("registry_page").unwrap()];
+        page.len = 0;
+        page.capacity = 0;
+
+        Ok(())
+    }
+
+    /// Migrate a registry account to the current layout. Migrating an account
+    /// that has the current version does nothing.
+    ///
+    /// The account grows to fit the current layout, and the payer funds its
+    /// rent. An account can only grow by [MAX_PERMITTED_DATA_INCREASE] bytes in
+    /// an instruction, so large accounts need the instruction to be repeated
+    /// until they are migrated.
+    pub fn migrate_registry(ctx: Context<MigrateRegistry>) -> Result<()> {
+        let registry_info = ctx.accounts.registry_account.to_account_info();
+        {
+            let data = registry_info.try_borrow_data()?;
+            check_authority(
+                &decode_registry_header(&data)?.0,
+                ctx.accounts.authority.key,
+                ctx.remaining_accounts,
+                None,
+            )?;
         }
-        ctx.accounts.registry_account.last_created_slot = recent_slot;
-        // Allocate space on the registry account if there are no more slots
-        let (len, capacity) = {
-            let registry = &ctx.accounts.registry_account;
-            (registry.len, registry.capacity)
+
+        let migrated_len = registry_migration_len(&registry_info.try_borrow_data()?)?;
+        let Some(migrated_len) = migrated_len else {
+            msg!("The account already has the current version");
+            return Ok(());
         };
-        let registry_info = ctx.accounts.registry_account.to_account_info();
-        let append_to_end = len == capacity;
-        if append_to_end {
-            // Needs realloc
-            let new_size = registry_info.data_len() + REGISTRY_ENTRY_SIZE;
-            let rent = Rent::get()?;
-            let transfer_amount = rent
-                .minimum_balance(new_size)
-                .checked_sub(registry_info.lamports())
-                .unwrap();
-            anchor_lang::system_program::transfer(
-                CpiContext::new(
-                    ctx.accounts.system_program.to_account_info(),
-                    anchor_lang::system_program::Transfer {
-                        from: ctx.accounts.payer.to_account_info(),
-                        to: registry_info,
-                    },
-                ),
-                transfer_amount,
+
+        if registry_info.data_len() < migrated_len {
+            let new_len = migrated_len.min(registry_info.data_len() + MAX_PERMITTED_DATA_INCREASE);
+            realloc_with_rent(
+                &registry_info,
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                new_len,
             )?;
-            // Increment the length of the registry
-            ctx.accounts.registry_account.len += 1;
+            if new_len < migrated_len {
+                msg!(
+                    "The account has grown to {} of {} bytes, repeat the instruction to continue",
+                    new_len,
+                    migrated_len
+                );
+                return Ok(());
+            }
+        }
+
+        let mut data = registry_info.try_borrow_mut_data()?;
+        migrate_registry_data(&mut data)
+    }
+
+    /// Propose a new authority for a registry account.
+    ///
+    /// The transfer only takes effect once the new authority accepts it with
+    /// [accept_authority]. Proposing the default pubkey cancels a pending transfer.
+    pub fn propose_authority(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            None,
+        )?;
+        ctx.accounts
+            .registry_account
+            .load_header_mut()?
+            .pending_authority = new_authority;
+
+        Ok(())
+    }
+
+    /// Accept the authority of a registry account as the proposed authority.
+    ///
+    /// The registry keeps its address, which is derived from [RegistryAccount::seed_authority].
+    /// The delegates of the previous authority are revoked, as the new authority
+    /// has not chosen them.
+    /// Lookup tables created by the registry have the registry account as their
+    /// authority, so the new authority can change them. Lookup tables created
+    /// before version 1 keep the previous authority as their authority, which
+    /// has to co-sign changes to them.
+    ///
+    /// A registry in multisig mode leaves it, as the new authority replaces
+    /// the multisig.
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let mut registry = ctx.accounts.registry_account.load_header_mut()?;
+        registry.authority = ctx.accounts.new_authority.key();
+        registry.pending_authority = Pubkey::default();
+        registry.delegates = Default::default();
+        registry.multisig = Multisig::default();
+
+        Ok(())
+    }
+
+    /// Close an empty registry account or its last page, and send the rent to
+    /// the recipient.
+    ///
+    /// If a page is provided, the page is closed, and it must be the last page
+    /// of the registry. Otherwise the registry account is closed, and it must
+    /// have no pages left.
+    pub fn close_registry_account(ctx: Context<CloseRegistryAccount>) -> Result<()> {
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            None,
+        )?;
+        let recipient = ctx.accounts.recipient.to_account_info();
+        match &ctx.accounts.registry_page {
+            Some(page) => {
+                {
+                    let mut registry = ctx.accounts.registry_account.load_header_mut()?;
+                    check_page_closeable(&registry, &*page.load_header()?)?;
+                    registry.pages -= 1;
+                }
+                page.close(recipient)
+            }
+            None => {
+                check_registry_closeable(&*ctx.accounts.registry_account.load_header()?)?;
+                ctx.accounts.registry_account.close(recipient)
+            }
         }
+    }
+
+    /// Remove the empty entries left behind by closed lookup tables from a
+    /// registry account, or from a page if one is provided.
+    ///
+    /// The remaining entries keep their order, and the capacity becomes the
+    /// number of entries. The account is shrunk accordingly, and the rent that
+    /// is no longer needed is sent to the registry authority. The registry
+    /// doesn't record who paid for each entry, and the authority owns the
+    /// registry, so a delegate can't send the rent elsewhere.
+    pub fn compact_registry(ctx: Context<CompactRegistry>) -> Result<()> {
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            Some(permissions::REMOVE),
+        )?;
+        let registry_info = match &ctx.accounts.registry_page {
+            Some(page) => page.to_account_info(),
+            None => ctx.accounts.registry_account.to_account_info(),
+        };
+        let removed = {
+            let (mut header, mut tables) =
+                registry_tables(&ctx.accounts.registry_account, &ctx.accounts.registry_page)?;
+            compact_entries(&mut *header, &mut tables)?
+        };
+        if removed == 0 {
+            msg!("The registry has no empty entries");
+            return Ok(());
+        }
+
+        let new_size = registry_info.data_len() - removed * REGISTRY_ENTRY_SIZE;
+        registry_info.realloc(new_size, false)?;
+        refund_excess_rent(
+            &registry_info,
+            &ctx.accounts.recipient,
+            Rent::get()?.minimum_balance(new_size),
+        )
+    }
+
+    /// Add a delegate that can sign for the authority with the given permissions.
+    /// If the delegate already exists, its permissions are replaced.
+    ///
+    /// See [crate::permissions] for the permissions that can be granted.
+    pub fn add_delegate(
+        ctx: Context<UpdateDelegates>,
+        delegate: Pubkey,
+        permissions: u8,
+    ) -> Result<()> {
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            None,
+        )?;
+        let mut registry = ctx.accounts.registry_account.load_header_mut()?;
+        if delegate == Pubkey::default() || delegate == registry.authority {
+            return err!(ErrorCode::InvalidDelegate);
+        }
+        if permissions == 0 || permissions & !crate::permissions::ALL != 0 {
+            msg!("Invalid permissions {:#b}", permissions);
+            return err!(ErrorCode::InvalidDelegate);
+        }
+        let delegates = &mut registry.delegates;
+        let slot = match delegates.iter().position(|d| d.key == delegate) {
+            Some(index) => index,
+            None => delegates
+                .iter()
+                .position(|d| d.key == Pubkey::default())
+                .ok_or(ErrorCode::TooManyDelegates)?,
+        };
+        delegates[slot] = Delegate {
+            key: delegate,
+            permissions,
+            reserved0: [0; 7],
+        };
+
+        Ok(())
+    }
+
+    /// Revoke a delegate, removing all its permissions.
+    pub fn revoke_delegate(ctx: Context<UpdateDelegates>, delegate: Pubkey) -> Result<()> {
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            None,
+        )?;
+        let mut registry = ctx.accounts.registry_account.load_header_mut()?;
+        let slot = registry
+            .delegates
+            .iter_mut()
+            .find(|d| d.key == delegate && delegate != Pubkey::default())
+            .ok_or(ErrorCode::InvalidDelegate)?;
+        *slot = Delegate::default();
+
+        Ok(())
+    }
 
-        // Create the lookup table
-        let (lookup_instruction, table) =
-            solana_address_lookup_table_program::instruction::create_lookup_table_signed(
-                ctx.accounts.authority.key(),
-                ctx.accounts.payer.key(),
-                recent_slot,
+    /// Set the signers and threshold of the multisig authority of a registry.
+    ///
+    /// In multisig mode, instructions that the authority signs need the
+    /// signatures of at least `threshold` signers. One signer signs as the
+    /// authority, and the others are passed as the remaining accounts.
+    /// Delegates still sign alone.
+    ///
+    /// Setting no signers and a threshold of 0 leaves multisig mode.
+    pub fn set_multisig(
+        ctx: Context<SetMultisig>,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            None,
+        )?;
+        if signers.len() > MAX_MULTISIG_SIGNERS
+            || threshold as usize > signers.len()
+            || (threshold == 0) != signers.is_empty()
+        {
+            msg!(
+                "Invalid threshold {} of {} signers",
+                threshold,
+                signers.len()
             );
-        if table != ctx.accounts.lookup_table.key() {
-            return err!(ErrorCode::InvalidLookupTable);
+            return err!(ErrorCode::InvalidMultisig);
+        }
+        for (i, signer) in signers.iter().enumerate() {
+            if signer == &Pubkey::default() || signers[..i].contains(signer) {
+                msg!("Invalid signer {}", signer);
+                return err!(ErrorCode::InvalidMultisig);
+            }
         }
 
-        invoke(
-            &lookup_instruction,
-            &[
-                ctx.accounts.lookup_table.to_account_info(),
-                ctx.accounts.authority.to_account_info(),
+        let mut registry = ctx.accounts.registry_account.load_header_mut()?;
+        let multisig = &mut registry.multisig;
+        multisig.signers = Default::default();
+        multisig.signers[..signers.len()].copy_from_slice(&signers);
+        multisig.threshold = threshold;
+
+        Ok(())
+    }
+
+    /// Create a lookup table in the registry
+    ///
+    /// The discriminator is chosen by the caller to tag the purpose of the
+    /// table, and must be greater than [discriminator::DEACTIVATED] without
+    /// the [discriminator::FROZEN] flag set.
+    ///
+    /// The lookup table is stored in the registry page if one is provided,
+    /// otherwise in the registry account. The registry account is the authority
+    /// of the lookup table, so that the lookup table stays in the control of
+    /// the registry when its authority is transferred, and a delegate never
+    /// becomes its authority.
+    ///
+    /// The recent slot must be later than the slot used to create the previous
+    /// lookup table in the registry.
+    pub fn create_lookup_table(
+        ctx: Context<CreateLookupTable>,
+        recent_slot: u64,
+        discriminator: u64,
+    ) -> Result<()> {
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            Some(permissions::CREATE),
+        )?;
+        let entry = create_table(
+            &ctx.accounts.registry_account,
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.lookup_table.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.address_lookup_table_program.to_account_info(),
+            recent_slot,
+            discriminator,
+        )?;
+        emit!(TableCreated {
+            registry: ctx.accounts.registry_account.key(),
+            table: entry.table,
+            slot: recent_slot,
+            discriminator,
+        });
+
+        // Add the account to the lookup registry
+        insert_entries(
+            &ctx.accounts.registry_account,
+            &ctx.accounts.registry_page,
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            vec![entry],
+        )
+    }
+
+    /// Create a lookup table in the registry, and extend it with addresses in
+    /// the same instruction. The lookup table is created as with
+    /// [create_lookup_table].
+    ///
+    /// The number of addresses is limited by the size of a transaction. The
+    /// lookup table is created empty if there are no addresses.
+    ///
+    /// Repeated addresses are skipped if `skip_duplicates` is set, and
+    /// rejected otherwise, as with [append_to_lookup_table].
+    pub fn create_lookup_table_with_addresses(
+        ctx: Context<CreateLookupTable>,
+        recent_slot: u64,
+        discriminator: u64,
+        addresses: Vec<Pubkey>,
+        skip_duplicates: bool,
+    ) -> Result<()> {
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            Some(permissions::CREATE),
+        )?;
+        let addresses = filter_duplicates(&[], addresses, skip_duplicates)?;
+        let entry = create_table(
+            &ctx.accounts.registry_account,
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.lookup_table.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.address_lookup_table_program.to_account_info(),
+            recent_slot,
+            discriminator,
+        )?;
+        emit!(TableCreated {
+            registry: ctx.accounts.registry_account.key(),
+            table: entry.table,
+            slot: recent_slot,
+            discriminator,
+        });
+
+        // Add the account to the lookup registry
+        insert_entries(
+            &ctx.accounts.registry_account,
+            &ctx.accounts.registry_page,
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            vec![entry],
+        )?;
+
+        // The lookup table program rejects extending a table with no addresses
+        if addresses.is_empty() {
+            return Ok(());
+        }
+        let table_authority = TableAuthority {
+            info: ctx.accounts.registry_account.to_account_info(),
+            registry_signs: true,
+        };
+        // The number of addresses is checked when the lookup table is extended
+        let count = addresses.len() as u16;
+        extend_table(
+            &table_authority,
+            &ctx.accounts.registry_account,
+            ctx.accounts.lookup_table.to_account_info(),
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.address_lookup_table_program.to_account_info(),
+            addresses,
+        )?;
+
+        emit!(TableExtended {
+            registry: ctx.accounts.registry_account.key(),
+            table: ctx.accounts.lookup_table.key(),
+            count,
+        });
+
+        Ok(())
+    }
+
+    /// Create several lookup tables in the registry, with the lookup tables
+    /// passed as the remaining accounts in the same order as the tables.
+    ///
+    /// The recent slots must be increasing, and later than the slot used to
+    /// create the previous lookup table in the registry. The registry account
+    /// or page is grown once for all the lookup tables that don't fit in its
+    /// empty entries.
+    pub fn create_lookup_tables<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateLookupTables<'info>>,
+        tables: Vec<NewLookupTable>,
+    ) -> Result<()> {
+        let (lookup_tables, cosigners) =
+            new_lookup_table_accounts(&tables, ctx.remaining_accounts)?;
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            cosigners,
+            Some(permissions::CREATE),
+        )?;
+
+        let mut entries = Vec::with_capacity(tables.len());
+        for (table, lookup_table) in tables.iter().zip(lookup_tables) {
+            entries.push(create_table(
+                &ctx.accounts.registry_account,
                 ctx.accounts.payer.to_account_info(),
+                lookup_table.clone(),
                 ctx.accounts.system_program.to_account_info(),
                 ctx.accounts.address_lookup_table_program.to_account_info(),
-            ],
+                table.recent_slot,
+                table.discriminator,
+            )?);
+            emit!(TableCreated {
+                registry: ctx.accounts.registry_account.key(),
+                table: lookup_table.key(),
+                slot: table.recent_slot,
+                discriminator: table.discriminator,
+            });
+        }
+
+        // Add the accounts to the lookup registry
+        insert_entries(
+            &ctx.accounts.registry_account,
+            &ctx.accounts.registry_page,
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            entries,
+        )
+    }
+
+    /// Add a lookup table that was created outside the registry to the registry.
+    ///
+    /// The authority of the lookup table must be the registry account or the
+    /// registry authority, and the lookup table must not be deactivated. The lookup table is stored in
+    /// the registry page if one is provided, otherwise in the registry account.
+    ///
+    /// Lookup tables that are already in the registry account or in the provided
+    /// page are rejected. Other pages are not checked.
+    pub fn import_lookup_table(ctx: Context<ImportLookupTable>, discriminator: u64) -> Result<()> {
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            None,
         )?;
+        check_new_discriminator(discriminator)?;
+
+        let table_key = ctx.accounts.lookup_table.key();
+        {
+            let data = ctx.accounts.lookup_table.try_borrow_data()?;
+            let table =
+                solana_address_lookup_table_program::state::AddressLookupTable::deserialize(&data)
+                    .map_err(|_| error!(ErrorCode::InvalidLookupTable))?;
+            check_importable(
+                &*ctx.accounts.registry_account.load_header()?,
+                &ctx.accounts.registry_account.key(),
+                &table.meta,
+            )?;
+        }
+
+        let duplicate = ctx
+            .accounts
+            .registry_account
+            .load_tables()?
+            .find_entry(&table_key)
+            .is_ok()
+            || match &ctx.accounts.registry_page {
+                Some(page) => page.load_tables()?.find_entry(&table_key).is_ok(),
+                None => false,
+            };
+        if duplicate {
+            return err!(ErrorCode::DuplicateLookupTable);
+        }
 
         // Add the account to the lookup registry
         let entry = RegistryEntry {
             discriminator,
-            table,
+            table: table_key,
+            metadata: EntryMetadata {
+                created_slot: Clock::get()?.slot,
+                ..Default::default()
+            },
+            deactivation_slot: 0,
+            close_recipient: Pubkey::default(),
         };
-        if append_to_end {
-            // Happy case, add to the end
-            let registry_info = ctx.accounts.registry_account.to_account_info();
-            let existing_len = registry_info.data_len();
-            registry_info.realloc(existing_len + REGISTRY_ENTRY_SIZE, true)?;
-            ctx.accounts.registry_account.tables.push(entry);
-        } else {
-            // Find a slot that's empty
-            let slot = ctx.accounts.registry_account.find_empty_entry()?;
-            *slot = entry;
+        insert_entries(
+            &ctx.accounts.registry_account,
+            &ctx.accounts.registry_page,
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            vec![entry],
+        )
+    }
+
+    /// Set the label of a lookup table in the registry, and the program that
+    /// it is used with. The label can be up to [MAX_LABEL_LEN] bytes.
+    pub fn set_entry_metadata(
+        ctx: Context<SetEntryMetadata>,
+        label: String,
+        program: Pubkey,
+    ) -> Result<()> {
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            Some(permissions::CREATE),
+        )?;
+        if label.len() > MAX_LABEL_LEN {
+            return err!(ErrorCode::InvalidLabel);
         }
-        ctx.accounts.registry_account.capacity += 1;
-        // Redundant check
-        if ctx.accounts.registry_account.len > ctx.accounts.registry_account.capacity {
-            return err!(ErrorCode::InvalidState);
+        let (_, mut tables) =
+            registry_tables(&ctx.accounts.registry_account, &ctx.accounts.registry_page)?;
+        let entry = tables.find_entry_mut(ctx.accounts.lookup_table.key)?;
+        if entry.discriminator == discriminator::EMPTY {
+            return err!(ErrorCode::InvalidLookupTable);
         }
+        entry.metadata.label = [0; MAX_LABEL_LEN];
+        entry.metadata.label[..label.len()].copy_from_slice(label.as_bytes());
+        entry.metadata.program = program;
 
         Ok(())
     }
 
     /// Add addresses to a lookup table.
+    ///
+    /// The discriminator must match the one that the lookup table was created with.
     pub fn append_to_lookup_table(
         ctx: Context<AppendToLookupTable>,
         addresses: Vec<Pubkey>,
-        _discriminator: u64,
+        discriminator: u64,
+        skip_duplicates: bool,
     ) -> Result<()> {
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            Some(permissions::APPEND),
+        )?;
         // Find the table in the registry
         {
-            let entry = ctx
-                .accounts
-                .registry_account
-                .find_entry(ctx.accounts.lookup_table.key)?;
+            let tables = match &ctx.accounts.registry_page {
+                Some(page) => page.load_tables()?,
+                None => ctx.accounts.registry_account.load_tables()?,
+            };
+            let entry = tables.find_entry(ctx.accounts.lookup_table.key)?;
 
             if entry.discriminator <= crate::discriminator::DEACTIVATED {
                 msg!("Cannot append to a lookup table that is deactivated");
                 return err!(ErrorCode::InvalidDiscriminator);
             }
-            // The discriminators should be compared in future versions
+            if entry.is_frozen() {
+                msg!("Cannot append to a lookup table that is frozen");
+                return err!(ErrorCode::TableFrozen);
+            }
+            if entry.discriminator != discriminator {
+                msg!(
+                    "Expected discriminator {}, found {}",
+                    discriminator,
+                    entry.discriminator
+                );
+                return err!(ErrorCode::DiscriminatorMismatch);
+            }
         }
 
-        let instruction = solana_address_lookup_table_program::instruction::extend_lookup_table(
-            ctx.accounts.lookup_table.key(),
-            ctx.accounts.authority.key(),
-            Some(ctx.accounts.payer.key()),
-            addresses,
-        );
+        let addresses = {
+            let data = ctx.accounts.lookup_table.try_borrow_data()?;
+            let table =
+                solana_address_lookup_table_program::state::AddressLookupTable::deserialize(&data)
+                    .map_err(|_| error!(ErrorCode::InvalidLookupTable))?;
+            filter_duplicates(&table.addresses, addresses, skip_duplicates)?
+        };
+        if addresses.is_empty() {
+            msg!("All the addresses are already in the lookup table");
+            return Ok(());
+        }
 
-        invoke(
-            &instruction,
-            &[
-                ctx.accounts.lookup_table.to_account_info(),
-                ctx.accounts.authority.to_account_info(),
-                ctx.accounts.payer.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-                ctx.accounts.address_lookup_table_program.to_account_info(),
-            ],
+        let table_authority = table_authority(
+            &ctx.accounts.registry_account,
+            &ctx.accounts.lookup_table,
+            &ctx.accounts.authority,
+            &ctx.accounts.table_authority,
+        )?;
+        // The number of addresses is checked when the lookup table is extended
+        let count = addresses.len() as u16;
+        extend_table(
+            &table_authority,
+            &ctx.accounts.registry_account,
+            ctx.accounts.lookup_table.to_account_info(),
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.address_lookup_table_program.to_account_info(),
+            addresses,
         )?;
 
+        emit!(TableExtended {
+            registry: ctx.accounts.registry_account.key(),
+            table: ctx.accounts.lookup_table.key(),
+            count,
+        });
+
         Ok(())
     }
 
     /// Remove a lookup table by either deactivating or deleting it depending on its
     /// current status.
+    ///
+    /// A deactivated lookup table can't be closed before the slot returned by
+    /// [RegistryEntry::closeable_at_slot], and errors with
+    /// [ErrorCode::TableStillCoolingDown] before then. If slots were skipped,
+    /// the lookup table program can still refuse to close it after that slot.
     pub fn remove_lookup_table(ctx: Context<RemoveLookupTable>) -> Result<()> {
-        // Find the table in the registry
-        let entry = ctx
-            .accounts
-            .registry_account
-            .find_entry_mut(ctx.accounts.lookup_table.key)?;
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            Some(permissions::REMOVE),
+        )?;
+        let table_authority = table_authority(
+            &ctx.accounts.registry_account,
+            &ctx.accounts.lookup_table,
+            &ctx.accounts.authority,
+            &ctx.accounts.table_authority,
+        )?;
+
+        // Find the table in the registry
+        let (mut header, mut tables) =
+            registry_tables(&ctx.accounts.registry_account, &ctx.accounts.registry_page)?;
+        let entry = tables.find_entry_mut(ctx.accounts.lookup_table.key)?;
+        if entry.is_frozen() {
+            msg!("Cannot remove a lookup table that is frozen");
+            return err!(ErrorCode::TableFrozen);
+        }
         // If the entry is active, deactivate it
         let to_delete = match entry.discriminator {
             discriminator::EMPTY => {
@@ -250,62 +953,786 @@ pub mod lookup_table_registry {
                 return err!(ErrorCode::InvalidState);
             }
             discriminator::DEACTIVATED => {
-                // mark as closed
-                entry.discriminator = discriminator::EMPTY;
-                entry.table = Pubkey::default();
-                // Decrement the registry length
-                ctx.accounts.registry_account.len =
-                    ctx.accounts.registry_account.len.checked_sub(1).unwrap();
+                close_entry(entry, &mut *header)?;
                 true
             }
             _ => {
                 // mark as deactivated
                 entry.discriminator = discriminator::DEACTIVATED;
+                entry.deactivation_slot = Clock::get()?.slot;
                 false
             }
         };
+        // The registry can't be borrowed while invoking the lookup table program
+        drop((header, tables));
 
         if to_delete {
             // Close the lookup table
             let lookup_instruction =
                 solana_address_lookup_table_program::instruction::close_lookup_table(
                     ctx.accounts.lookup_table.key(),
-                    ctx.accounts.authority.key(),
+                    table_authority.info.key(),
                     ctx.accounts.recipient.key(),
                 );
 
-            invoke(
+            table_authority.invoke(
                 &lookup_instruction,
                 &[
                     ctx.accounts.lookup_table.to_account_info(),
-                    ctx.accounts.authority.to_account_info(),
+                    table_authority.info.clone(),
                     ctx.accounts.recipient.to_account_info(),
                     ctx.accounts.system_program.to_account_info(),
                     ctx.accounts.address_lookup_table_program.to_account_info(),
                 ],
+                &ctx.accounts.registry_account,
             )?;
+
+            emit!(TableClosed {
+                registry: ctx.accounts.registry_account.key(),
+                table: ctx.accounts.lookup_table.key(),
+            });
         } else {
             // Deactivate the lookup table
             let lookup_instruction =
                 solana_address_lookup_table_program::instruction::deactivate_lookup_table(
                     ctx.accounts.lookup_table.key(),
-                    ctx.accounts.authority.key(),
+                    table_authority.info.key(),
                 );
 
-            invoke(
+            table_authority.invoke(
                 &lookup_instruction,
                 &[
                     ctx.accounts.lookup_table.to_account_info(),
-                    ctx.accounts.authority.to_account_info(),
+                    table_authority.info.clone(),
                     ctx.accounts.address_lookup_table_program.to_account_info(),
                 ],
+                &ctx.accounts.registry_account,
             )?;
+
+            emit!(TableDeactivated {
+                registry: ctx.accounts.registry_account.key(),
+                table: ctx.accounts.lookup_table.key(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Freeze a lookup table so that it can no longer be modified or removed.
+    ///
+    /// The lookup table keeps its discriminator, and is marked as
+    /// [discriminator::FROZEN] in the registry. Empty lookup tables cannot be frozen.
+    pub fn freeze_lookup_table(ctx: Context<FreezeLookupTable>) -> Result<()> {
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            None,
+        )?;
+        // Find the table in the registry
+        {
+            let (_, mut tables) =
+                registry_tables(&ctx.accounts.registry_account, &ctx.accounts.registry_page)?;
+            let entry = tables.find_entry_mut(ctx.accounts.lookup_table.key)?;
+            if entry.discriminator <= discriminator::DEACTIVATED {
+                msg!("Cannot freeze a lookup table that is deactivated");
+                return err!(ErrorCode::InvalidDiscriminator);
+            }
+            if entry.is_frozen() {
+                msg!("The lookup table is already frozen");
+                return err!(ErrorCode::TableFrozen);
+            }
+            entry.discriminator |= discriminator::FROZEN;
+        }
+
+        let table_authority = table_authority(
+            &ctx.accounts.registry_account,
+            &ctx.accounts.lookup_table,
+            &ctx.accounts.authority,
+            &ctx.accounts.table_authority,
+        )?;
+        let lookup_instruction =
+            solana_address_lookup_table_program::instruction::freeze_lookup_table(
+                ctx.accounts.lookup_table.key(),
+                table_authority.info.key(),
+            );
+
+        table_authority.invoke(
+            &lookup_instruction,
+            &[
+                ctx.accounts.lookup_table.to_account_info(),
+                table_authority.info.clone(),
+                ctx.accounts.address_lookup_table_program.to_account_info(),
+            ],
+            &ctx.accounts.registry_account,
+        )?;
+
+        Ok(())
+    }
+
+    /// Set the recipient of a lookup table's lamports, so that anyone can
+    /// close it with [close_deactivated_table] once the authority has
+    /// deactivated it and it has cooled down. Without a recipient, only the
+    /// authority can close the lookup table.
+    ///
+    /// The registry account must be the authority of the lookup table, so
+    /// that the program can sign to close it.
+    pub fn set_auto_close(
+        ctx: Context<SetAutoClose>,
+        close_recipient: Option<Pubkey>,
+    ) -> Result<()> {
+        check_authority(
+            &*ctx.accounts.registry_account.load_header()?,
+            ctx.accounts.authority.key,
+            ctx.remaining_accounts,
+            Some(permissions::REMOVE),
+        )?;
+        if close_recipient.is_some() {
+            let data = ctx.accounts.lookup_table.try_borrow_data()?;
+            let table =
+                solana_address_lookup_table_program::state::AddressLookupTable::deserialize(&data)
+                    .map_err(|_| error!(ErrorCode::InvalidLookupTable))?;
+            if table.meta.authority != Some(ctx.accounts.registry_account.key()) {
+                msg!("Only lookup tables that have the registry account as their authority can close automatically");
+                return err!(ErrorCode::RegistryNotTableAuthority);
+            }
+        }
+
+        let (_, mut tables) =
+            registry_tables(&ctx.accounts.registry_account, &ctx.accounts.registry_page)?;
+        let entry = tables.find_entry_mut(ctx.accounts.lookup_table.key)?;
+        if entry.is_frozen() {
+            msg!("Cannot close a lookup table that is frozen");
+            return err!(ErrorCode::TableFrozen);
+        }
+        entry.close_recipient = close_recipient.unwrap_or_default();
+
+        Ok(())
+    }
+
+    /// Close a deactivated lookup table that has a close recipient, once it
+    /// has cooled down. Anyone can close it, and its lamports go to the
+    /// recipient set with [set_auto_close].
+    pub fn close_deactivated_table(ctx: Context<CloseDeactivatedTable>) -> Result<()> {
+        {
+            let (mut header, mut tables) =
+                registry_tables(&ctx.accounts.registry_account, &ctx.accounts.registry_page)?;
+            let entry = tables.find_entry_mut(ctx.accounts.lookup_table.key)?;
+            if !entry.is_auto_close() {
+                msg!("Only the authority can close the lookup table");
+                return err!(ErrorCode::AutoCloseDisabled);
+            }
+            if entry.close_recipient != ctx.accounts.recipient.key() {
+                msg!("The lamports must go to {}", entry.close_recipient);
+                return err!(ErrorCode::InvalidRecipient);
+            }
+            if entry.discriminator != discriminator::DEACTIVATED {
+                msg!("The authority has not deactivated the lookup table");
+                return err!(ErrorCode::InvalidDiscriminator);
+            }
+            close_entry(entry, &mut *header)?;
+        }
+
+        // Only lookup tables that have the registry account as their authority
+        // can have a close recipient
+        let lookup_instruction =
+            solana_address_lookup_table_program::instruction::close_lookup_table(
+                ctx.accounts.lookup_table.key(),
+                ctx.accounts.registry_account.key(),
+                ctx.accounts.recipient.key(),
+            );
+        let table_authority = TableAuthority {
+            info: ctx.accounts.registry_account.to_account_info(),
+            registry_signs: true,
+        };
+        table_authority.invoke(
+            &lookup_instruction,
+            &[
+                ctx.accounts.lookup_table.to_account_info(),
+                table_authority.info.clone(),
+                ctx.accounts.recipient.to_account_info(),
+                ctx.accounts.address_lookup_table_program.to_account_info(),
+            ],
+            &ctx.accounts.registry_account,
+        )?;
+
+        emit!(TableClosed {
+            registry: ctx.accounts.registry_account.key(),
+            table: ctx.accounts.lookup_table.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Remove the entry of a lookup table that no longer exists, because it
+    /// was closed outside the registry. Anyone can prune the entry once the
+    /// lookup table account is gone, or is not owned by the lookup table
+    /// program.
+    pub fn prune_entry(ctx: Context<PruneEntry>) -> Result<()> {
+        let lookup_table = &ctx.accounts.lookup_table;
+        if lookup_table.owner == &solana_address_lookup_table_program::ID
+            && lookup_table.lamports() > 0
+        {
+            msg!("The lookup table still exists");
+            return err!(ErrorCode::LookupTableExists);
+        }
+
+        let (mut header, mut tables) =
+            registry_tables(&ctx.accounts.registry_account, &ctx.accounts.registry_page)?;
+        let entry = tables.find_entry_mut(lookup_table.key)?;
+        if entry.discriminator == discriminator::EMPTY {
+            msg!("The entry is already empty");
+            return err!(ErrorCode::InvalidLookupTable);
+        }
+        clear_entry(entry, &mut *header);
+
+        emit!(EntryPruned {
+            registry: ctx.accounts.registry_account.key(),
+            table: lookup_table.key(),
+        });
+
+        Ok(())
+    }
+}
+
+/// Initialize an empty registry account
+#[cfg(feature = "program")]
+fn init_registry(
+    registry: &mut RegistryAccount,
+    authority: Pubkey,
+    namespace: &[u8],
+    bump: u8,
+) -> Result<()> {
+    if namespace.len() > MAX_NAMESPACE_LEN {
+        return err!(ErrorCode::InvalidNamespace);
+    }
+    let clock = Clock::get()?;
+    registry.authority = authority;
+    registry.seed_authority = authority;
+    registry.namespace = [0; MAX_NAMESPACE_LEN];
+    registry.namespace[..namespace.len()].copy_from_slice(namespace);
+    registry.namespace_len = namespace.len() as u8;
+    registry.pending_authority = Pubkey::default();
+    registry.delegates = Default::default();
+    registry.multisig = Multisig::default();
+    registry.version = REGISTRY_VERSION;
+    registry.len = 0;
+    registry.capacity = 0;
+    registry.last_created_slot = clock.slot;
+    registry.seed = [bump];
+    registry.pages = 0;
+
+    Ok(())
+}
+
+/// Check that the authority of a registry has signed, or a delegate with the
+/// permissions if any are given.
+///
+/// In multisig mode, the signer and the cosigners that have signed must
+/// include at least the threshold of signers of the multisig.
+#[cfg(feature = "program")]
+fn check_authority(
+    registry: &RegistryAccount,
+    signer: &Pubkey,
+    cosigners: &[AccountInfo],
+    permissions: Option<u8>,
+) -> Result<()> {
+    if matches!(permissions, Some(p) if registry.is_delegate(signer, p)) {
+        return Ok(());
+    }
+    if !registry.multisig.is_enabled() {
+        if signer != &registry.authority {
+            return err!(anchor_lang::error::ErrorCode::ConstraintRaw);
         }
+        return Ok(());
+    }
+
+    let signers = cosigners
+        .iter()
+        .filter(|cosigner| cosigner.is_signer)
+        .map(|cosigner| cosigner.key);
+    let approvals = registry
+        .multisig
+        .approvals(std::iter::once(signer).chain(signers));
+    if approvals < registry.multisig.threshold as usize {
+        msg!(
+            "{} of the {} signers required have signed",
+            approvals,
+            registry.multisig.threshold
+        );
+        return err!(ErrorCode::MultisigThresholdNotMet);
+    }
+    Ok(())
+}
+
+/// Check that a discriminator can be used for a new entry. It can't be one of
+/// the reserved states, or have the frozen flag set.
+#[cfg(feature = "program")]
+fn check_new_discriminator(discriminator: u64) -> Result<()> {
+    if discriminator <= discriminator::DEACTIVATED || discriminator & discriminator::FROZEN != 0 {
+        return err!(ErrorCode::InvalidDiscriminator);
+    }
+    Ok(())
+}
+
+/// Check that a lookup table can be imported into a registry. Its authority must
+/// be the registry account or the registry authority, and it must not be
+/// deactivated.
+#[cfg(feature = "program")]
+fn check_importable(
+    registry: &RegistryAccount,
+    registry_address: &Pubkey,
+    meta: &solana_address_lookup_table_program::state::LookupTableMeta,
+) -> Result<()> {
+    if meta.authority != Some(*registry_address) && meta.authority != Some(registry.authority) {
+        msg!("The lookup table authority is neither the registry nor its authority");
+        return err!(ErrorCode::InvalidLookupTable);
+    }
+    if meta.deactivation_slot != u64::MAX {
+        msg!("Cannot import a lookup table that is deactivated");
+        return err!(ErrorCode::InvalidLookupTable);
+    }
+    Ok(())
+}
+
+/// Record the slot that a lookup table is created with, which must be later
+/// than the slot used to create the previous lookup table in the registry
+#[cfg(feature = "program")]
+fn set_last_created_slot(registry: &mut RegistryAccount, recent_slot: u64) -> Result<()> {
+    if recent_slot <= registry.last_created_slot {
+        msg!(
+            "The recent slot {} must be later than the last slot used {}",
+            recent_slot,
+            registry.last_created_slot
+        );
+        return err!(ErrorCode::InvalidSlot);
+    }
+    registry.last_created_slot = recent_slot;
+    Ok(())
+}
+
+/// The authority of a lookup table, used to invoke the lookup table program
+#[cfg(feature = "program")]
+struct TableAuthority<'info> {
+    /// The account of the authority
+    info: AccountInfo<'info>,
+    /// Whether the authority is the registry account, which the program signs for
+    registry_signs: bool,
+}
 
+#[cfg(feature = "program")]
+impl<'info> TableAuthority<'info> {
+    /// Invoke the lookup table program, signing for the registry account if it
+    /// is the authority
+    fn invoke(
+        &self,
+        instruction: &solana_program::instruction::Instruction,
+        account_infos: &[AccountInfo<'info>],
+        registry: &AccountLoader<RegistryAccount>,
+    ) -> Result<()> {
+        if self.registry_signs {
+            let registry = registry.load_header()?;
+            solana_program::program::invoke_signed(
+                instruction,
+                account_infos,
+                &[&registry.signer_seeds()],
+            )?;
+        } else {
+            solana_program::program::invoke(instruction, account_infos)?;
+        }
         Ok(())
     }
 }
 
+/// The authority of an existing lookup table. This is the registry account if
+/// it is the authority of the lookup table, otherwise the registry authority
+/// unless another table authority is provided.
+#[cfg(feature = "program")]
+fn table_authority<'info>(
+    registry: &AccountLoader<'info, RegistryAccount>,
+    lookup_table: &AccountInfo<'info>,
+    authority: &Signer<'info>,
+    table_authority: &Option<Signer<'info>>,
+) -> Result<TableAuthority<'info>> {
+    let data = lookup_table.try_borrow_data()?;
+    let table = solana_address_lookup_table_program::state::AddressLookupTable::deserialize(&data)
+        .map_err(|_| error!(ErrorCode::InvalidLookupTable))?;
+    if registry_is_table_authority(
+        &*registry.load_header()?,
+        &registry.key(),
+        table.meta.authority,
+        authority.key,
+    )? {
+        return Ok(TableAuthority {
+            info: registry.to_account_info(),
+            registry_signs: true,
+        });
+    }
+    Ok(TableAuthority {
+        info: table_authority
+            .as_ref()
+            .unwrap_or(authority)
+            .to_account_info(),
+        registry_signs: false,
+    })
+}
+
+/// Mark the entry of a deactivated lookup table as empty before closing the
+/// lookup table, and decrement the length of its registry account or page.
+#[cfg(feature = "program")]
+fn close_entry(entry: &mut RegistryEntry, header: &mut dyn RegistryHeader) -> Result<()> {
+    check_cooled_down(entry, Clock::get()?.slot)?;
+    clear_entry(entry, header);
+    Ok(())
+}
+
+/// Mark an entry as empty so that a new lookup table can take it, and
+/// decrement the length of its registry account or page
+#[cfg(feature = "program")]
+fn clear_entry(entry: &mut RegistryEntry, header: &mut dyn RegistryHeader) {
+    *entry = RegistryEntry {
+        discriminator: discriminator::EMPTY,
+        table: Pubkey::default(),
+        metadata: EntryMetadata::default(),
+        deactivation_slot: 0,
+        close_recipient: Pubkey::default(),
+    };
+    let (len, _) = header.len_and_capacity_mut();
+    *len = len.checked_sub(1).unwrap();
+}
+
+/// Fail early instead of in the lookup table program if a deactivated lookup
+/// table can't have cooled down yet. Passing this check doesn't mean that the
+/// lookup table has cooled down when slots were skipped.
+#[cfg(feature = "program")]
+fn check_cooled_down(entry: &RegistryEntry, slot: u64) -> Result<()> {
+    if let Some(closeable_at_slot) = entry.closeable_at_slot() {
+        if slot < closeable_at_slot {
+            msg!(
+                "The lookup table can be closed from slot {}",
+                closeable_at_slot
+            );
+            return err!(ErrorCode::TableStillCoolingDown);
+        }
+    }
+    Ok(())
+}
+
+/// Move the entries of a registry account or page before its empty entries,
+/// keeping their order, and return the number of empty entries that can be
+/// removed from the end. The capacity becomes the number of entries.
+#[cfg(feature = "program")]
+fn compact_entries(header: &mut dyn RegistryHeader, tables: &mut [RegistryEntry]) -> Result<usize> {
+    let mut remaining = 0;
+    for index in 0..tables.len() {
+        if tables[index].discriminator != discriminator::EMPTY {
+            tables[remaining] = tables[index];
+            remaining += 1;
+        }
+    }
+
+    let (len, capacity) = header.len_and_capacity_mut();
+    // Redundant check
+    if *len as usize != remaining {
+        return err!(ErrorCode::InvalidState);
+    }
+    *capacity = remaining as u8;
+    Ok(tables.len() - remaining)
+}
+
+/// Send the lamports of an account above its rent exempt balance to the recipient
+#[cfg(feature = "program")]
+fn refund_excess_rent(
+    account: &AccountInfo,
+    recipient: &AccountInfo,
+    rent_exempt_balance: u64,
+) -> Result<()> {
+    let excess = account.lamports().saturating_sub(rent_exempt_balance);
+    **account.try_borrow_mut_lamports()? -= excess;
+    **recipient.try_borrow_mut_lamports()? += excess;
+    Ok(())
+}
+
+/// Check that a page is the last page of the registry, and has no lookup tables
+#[cfg(feature = "program")]
+fn check_page_closeable(registry: &RegistryAccount, page: &RegistryPage) -> Result<()> {
+    if page.page != registry.pages {
+        msg!("Pages must be closed starting from the last page");
+        return err!(ErrorCode::RegistryNotEmpty);
+    }
+    if page.len != 0 {
+        msg!("The page has {} lookup tables", page.len);
+        return err!(ErrorCode::RegistryNotEmpty);
+    }
+    Ok(())
+}
+
+/// Check that a registry account has no lookup tables and no pages left
+#[cfg(feature = "program")]
+fn check_registry_closeable(registry: &RegistryAccount) -> Result<()> {
+    if registry.len != 0 || registry.pages != 0 {
+        msg!(
+            "The registry has {} lookup tables and {} pages",
+            registry.len,
+            registry.pages
+        );
+        return err!(ErrorCode::RegistryNotEmpty);
+    }
+    Ok(())
+}
+
+/// Whether the registry account is the authority of a lookup table. Only the
+/// registry authority can change lookup tables that it is not the authority of,
+/// so a delegate can't change them.
+#[cfg(feature = "program")]
+fn registry_is_table_authority(
+    registry: &RegistryAccount,
+    registry_address: &Pubkey,
+    table_authority: Option<Pubkey>,
+    signer: &Pubkey,
+) -> Result<bool> {
+    if table_authority.as_ref() == Some(registry_address) {
+        return Ok(true);
+    }
+    if !registry.is_authority(signer) {
+        msg!("Delegates can only change lookup tables owned by the registry account");
+        return err!(ErrorCode::RegistryNotTableAuthority);
+    }
+    Ok(false)
+}
+
+/// The header and the entries of a registry account or page, borrowed for mutation
+#[cfg(feature = "program")]
+type TablesMut<'a> = (RefMut<'a, dyn RegistryHeader>, RefMut<'a, [RegistryEntry]>);
+
+/// The registry page if one is provided, otherwise the registry account,
+/// borrowed with its entries for mutation
+#[cfg(feature = "program")]
+fn registry_tables<'a>(
+    registry_account: &'a AccountLoader<RegistryAccount>,
+    registry_page: &'a Option<AccountLoader<RegistryPage>>,
+) -> Result<TablesMut<'a>> {
+    Ok(match registry_page {
+        Some(page) => {
+            let (header, tables) = page.load_tables_mut()?;
+            (RefMut::map(header, |header| header as _), tables)
+        }
+        None => {
+            let (header, tables) = registry_account.load_tables_mut()?;
+            (RefMut::map(header, |header| header as _), tables)
+        }
+    })
+}
+
+/// The accounts of the lookup tables to create, which must be the first
+/// remaining accounts in the same order as the tables, and the cosigners of a
+/// multisig authority that follow them
+#[cfg(feature = "program")]
+fn new_lookup_table_accounts<'a, 'info>(
+    tables: &[NewLookupTable],
+    remaining_accounts: &'a [AccountInfo<'info>],
+) -> Result<(&'a [AccountInfo<'info>], &'a [AccountInfo<'info>])> {
+    if tables.is_empty() || tables.len() > remaining_accounts.len() {
+        msg!(
+            "Expected a lookup table account for each of the {} tables",
+            tables.len()
+        );
+        return err!(ErrorCode::InvalidLookupTable);
+    }
+    Ok(remaining_accounts.split_at(tables.len()))
+}
+
+/// Create a lookup table with the registry account as its authority, and
+/// return the entry of the lookup table for the registry.
+///
+/// The recent slot must be later than the slot used to create the previous
+/// lookup table in the registry.
+#[cfg(feature = "program")]
+fn create_table<'info>(
+    registry: &AccountLoader<'info, RegistryAccount>,
+    payer: AccountInfo<'info>,
+    lookup_table: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    address_lookup_table_program: AccountInfo<'info>,
+    recent_slot: u64,
+    discriminator: u64,
+) -> Result<RegistryEntry> {
+    check_new_discriminator(discriminator)?;
+    set_last_created_slot(&mut *registry.load_header_mut()?, recent_slot)?;
+
+    let (lookup_instruction, table) =
+        solana_address_lookup_table_program::instruction::create_lookup_table_signed(
+            registry.key(),
+            payer.key(),
+            recent_slot,
+        );
+    if table != lookup_table.key() {
+        return err!(ErrorCode::InvalidLookupTable);
+    }
+
+    solana_program::program::invoke_signed(
+        &lookup_instruction,
+        &[
+            lookup_table,
+            registry.to_account_info(),
+            payer,
+            system_program,
+            address_lookup_table_program,
+        ],
+        &[&registry.load_header()?.signer_seeds()],
+    )?;
+
+    Ok(RegistryEntry {
+        discriminator,
+        table,
+        metadata: EntryMetadata {
+            created_slot: Clock::get()?.slot,
+            ..Default::default()
+        },
+        deactivation_slot: 0,
+        close_recipient: Pubkey::default(),
+    })
+}
+
+/// Remove the addresses that are already in a lookup table, or that are repeated,
+/// keeping the order of the remaining addresses. If duplicates are not skipped,
+/// they are rejected instead.
+///
+/// The addresses are sorted so that each address of the lookup table is found
+/// with a binary search. For a full lookup table of 256 addresses, this is
+/// about 256 * log2(addresses) comparisons, instead of 256 * addresses.
+#[cfg(feature = "program")]
+fn filter_duplicates(
+    existing: &[Pubkey],
+    addresses: Vec<Pubkey>,
+    skip_duplicates: bool,
+) -> Result<Vec<Pubkey>> {
+    // Sorting by the index too keeps the first of repeated addresses first
+    let mut sorted = addresses.iter().copied().zip(0..).collect::<Vec<_>>();
+    sorted.sort_unstable();
+    let mut duplicates = vec![false; addresses.len()];
+    for pair in sorted.windows(2) {
+        if pair[0].0 == pair[1].0 {
+            duplicates[pair[1].1] = true;
+        }
+    }
+    for address in existing {
+        let first = sorted.partition_point(|(a, _)| a < address);
+        if let Some((a, index)) = sorted.get(first) {
+            if a == address {
+                duplicates[*index] = true;
+            }
+        }
+    }
+
+    if !skip_duplicates {
+        if let Some(index) = duplicates.iter().position(|duplicate| *duplicate) {
+            msg!("Duplicate address {}", addresses[index]);
+            return err!(ErrorCode::DuplicateAddress);
+        }
+    }
+    Ok(addresses
+        .into_iter()
+        .zip(duplicates)
+        .filter_map(|(address, duplicate)| (!duplicate).then_some(address))
+        .collect())
+}
+
+/// Extend a lookup table with addresses, with the payer funding its rent
+#[cfg(feature = "program")]
+fn extend_table<'info>(
+    table_authority: &TableAuthority<'info>,
+    registry: &AccountLoader<RegistryAccount>,
+    lookup_table: AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    address_lookup_table_program: AccountInfo<'info>,
+    addresses: Vec<Pubkey>,
+) -> Result<()> {
+    check_address_count(addresses.len())?;
+    let instruction = solana_address_lookup_table_program::instruction::extend_lookup_table(
+        lookup_table.key(),
+        table_authority.info.key(),
+        Some(payer.key()),
+        addresses,
+    );
+
+    table_authority.invoke(
+        &instruction,
+        &[
+            lookup_table,
+            table_authority.info.clone(),
+            payer,
+            system_program,
+            address_lookup_table_program,
+        ],
+        registry,
+    )
+}
+
+/// Check that a lookup table can hold the number of addresses. Lookup tables
+/// that already have addresses are checked by the lookup table program.
+#[cfg(feature = "program")]
+fn check_address_count(count: usize) -> Result<()> {
+    if count > solana_address_lookup_table_program::state::LOOKUP_TABLE_MAX_ADDRESSES {
+        msg!("A lookup table cannot hold {} addresses", count);
+        return err!(ErrorCode::TooManyAddresses);
+    }
+    Ok(())
+}
+
+/// Add entries to the registry page if one is provided, otherwise to the
+/// registry account, reusing empty entries if there are any. The account is
+/// grown once for the remaining entries, and the payer funds its rent.
+#[cfg(feature = "program")]
+fn insert_entries<'info>(
+    registry_account: &AccountLoader<'info, RegistryAccount>,
+    registry_page: &Option<AccountLoader<'info, RegistryPage>>,
+    payer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    entries: Vec<RegistryEntry>,
+) -> Result<()> {
+    let count = entries.len();
+    // Fill the empty slots first
+    let (reused, capacity) = {
+        let (mut header, mut tables) = registry_tables(registry_account, registry_page)?;
+        let (len, capacity) = header.len_and_capacity_mut();
+        // Registries migrated from older versions can have more entries
+        if *len as usize + count > MAX_REGISTRY_ENTRIES {
+            return err!(ErrorCode::TooManyEntries);
+        }
+        let reused = (capacity.saturating_sub(*len) as usize).min(count);
+        let old_capacity = *capacity as usize;
+        *len += count as u8;
+        *capacity += (count - reused) as u8;
+        // Redundant check
+        if len > capacity {
+            return err!(ErrorCode::InvalidState);
+        }
+
+        for entry in &entries[..reused] {
+            *tables.find_empty_entry()? = *entry;
+        }
+        (reused, old_capacity)
+    };
+    if reused == count {
+        return Ok(());
+    }
+
+    // Allocate space on the account for the rest, and add them to the end.
+    // The account can't be borrowed while it is resized.
+    let registry_info = match registry_page {
+        Some(page) => page.to_account_info(),
+        None => registry_account.to_account_info(),
+    };
+    let new_size = registry_info.data_len() + (count - reused) * REGISTRY_ENTRY_SIZE;
+    realloc_with_rent(&registry_info, payer, system_program, new_size)?;
+    let (_, mut tables) = registry_tables(registry_account, registry_page)?;
+    tables[capacity..].copy_from_slice(&entries[reused..]);
+
+    Ok(())
+}
+
 /// Accounts for the instruction to initialize a lookup table registry account
 #[derive(Accounts)]
 pub struct InitRegistryAccount<'info> {
@@ -323,15 +1750,45 @@ pub struct InitRegistryAccount<'info> {
         payer = payer,
         space = 8 + std::mem::size_of::<RegistryAccount>())
     ]
-    pub registry_account: Box<Account<'info, RegistryAccount>>,
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
 
     /// The system program
     pub system_program: Program<'info, System>,
 }
 
-/// Accounts for the instruction to create a lookup table in the registry
+/// Resize an account, and fund its rent from the payer
+#[cfg(feature = "program")]
+fn realloc_with_rent<'info>(
+    account: &AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    new_size: usize,
+) -> Result<()> {
+    let rent = Rent::get()?;
+    let transfer_amount = rent
+        .minimum_balance(new_size)
+        .saturating_sub(account.lamports());
+    if transfer_amount > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program,
+                anchor_lang::system_program::Transfer {
+                    from: payer,
+                    to: account.clone(),
+                },
+            ),
+            transfer_amount,
+        )?;
+    }
+    account.realloc(new_size, true)?;
+    Ok(())
+}
+
+/// Accounts for the instruction to initialize a lookup table registry account
+/// in a namespace
 #[derive(Accounts)]
-pub struct CreateLookupTable<'info> {
+#[instruction(namespace: String)]
+pub struct InitNamespacedRegistryAccount<'info> {
     /// The authority of the registry account
     pub authority: Signer<'info>,
 
@@ -339,26 +1796,22 @@ pub struct CreateLookupTable<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    /// The registry account of the authority
-    #[account(mut, constraint = registry_account.authority == authority.key())]
-    pub registry_account: Box<Account<'info, RegistryAccount>>,
-
-    /// The lookup table being created
-    /// CHECK: the account will be validated by the lookup table program
-    #[account(mut)]
-    pub lookup_table: AccountInfo<'info>,
-
-    /// CHECK: the account will be validated by the lookup table program
-    #[account(address = solana_address_lookup_table_program::ID)]
-    pub address_lookup_table_program: AccountInfo<'info>,
+    /// The registry account of the authority in the namespace
+    #[account(init,
+        seeds = [authority.key.as_ref(), namespace.as_bytes()],
+        bump,
+        payer = payer,
+        space = 8 + std::mem::size_of::<RegistryAccount>())
+    ]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
 
     /// The system program
     pub system_program: Program<'info, System>,
 }
 
-/// Accounts for the instruction to append entries to a lookup table
+/// Accounts for the instruction to add an overflow page to a registry account
 #[derive(Accounts)]
-pub struct AppendToLookupTable<'info> {
+pub struct AddRegistryPage<'info> {
     /// The authority of the registry account
     pub authority: Signer<'info>,
 
@@ -367,49 +1820,396 @@ pub struct AppendToLookupTable<'info> {
     pub payer: Signer<'info>,
 
     /// The registry account of the authority
-    #[account(constraint = registry_account.authority == authority.key())]
-    pub registry_account: Box<Account<'info, RegistryAccount>>,
-
-    /// The lookup table being created
-    /// CHECK: the account will be validated by the lookup table program
-    #[account(mut)]
-    pub lookup_table: AccountInfo<'info>,
+    #[account(mut, constraint = registry_account.load_header()?.can_sign(authority.key, permissions::CREATE))]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
 
-    /// CHECK: the account will be validated by the lookup table program
-    #[account(address = solana_address_lookup_table_program::ID)]
-    pub address_lookup_table_program: AccountInfo<'info>,
+    /// The page being added
+    #[account(init,
+        seeds = [registry_account.key().as_ref(), &[registry_account.load_header()?.next_page()?]],
+        bump,
+        payer = payer,
+        space = 8 + std::mem::size_of::<RegistryPage>())
+    ]
+    pub registry_page: AccountLoader<'info, RegistryPage>,
 
     /// The system program
     pub system_program: Program<'info, System>,
 }
 
-/// Accounts for the instruction to remove a lookup table
+/// Accounts for the instruction to migrate a registry account
 #[derive(Accounts)]
-pub struct RemoveLookupTable<'info> {
+pub struct MigrateRegistry<'info> {
     /// The authority of the registry account
     pub authority: Signer<'info>,
 
-    /// The recipient of lamports
+    /// The payer of the transaction
     #[account(mut)]
-    pub recipient: Signer<'info>,
+    pub payer: Signer<'info>,
 
     /// The registry account of the authority
-    #[account(mut, constraint = registry_account.authority == authority.key())]
-    pub registry_account: Box<Account<'info, RegistryAccount>>,
-
-    /// The lookup table being closed
-    /// CHECK: the account will be validated by the lookup table program
-    #[account(mut)]
-    pub lookup_table: AccountInfo<'info>,
-
-    /// CHECK: the account will be validated by the lookup table program
-    #[account(address = solana_address_lookup_table_program::ID)]
-    pub address_lookup_table_program: AccountInfo<'info>,
+    /// CHECK: the account is decoded according to its version
+    #[account(mut, owner = crate::ID)]
+    pub registry_account: AccountInfo<'info>,
 
     /// The system program
     pub system_program: Program<'info, System>,
 }
 
+/// Accounts for the instruction to propose a new registry authority
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    /// The authority of the registry account
+    pub authority: Signer<'info>,
+
+    /// The registry account of the authority
+    #[account(mut, constraint = registry_account.load_header()?.is_authority(authority.key))]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+}
+
+/// Accounts for the instruction to accept the authority of a registry
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    /// The proposed authority of the registry account
+    pub new_authority: Signer<'info>,
+
+    /// The registry account being transferred
+    #[account(mut,
+        constraint = registry_account.load_header()?.pending_authority != Pubkey::default(),
+        constraint = registry_account.load_header()?.pending_authority == new_authority.key())
+    ]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+}
+
+/// Accounts for the instruction to set the multisig authority of a registry
+#[derive(Accounts)]
+pub struct SetMultisig<'info> {
+    /// The authority of the registry account, or a signer of its multisig
+    pub authority: Signer<'info>,
+
+    /// The registry account of the authority
+    #[account(mut, constraint = registry_account.load_header()?.is_authority(authority.key))]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+}
+
+/// Accounts for the instructions to add and revoke delegates
+#[derive(Accounts)]
+pub struct UpdateDelegates<'info> {
+    /// The authority of the registry account
+    pub authority: Signer<'info>,
+
+    /// The registry account of the authority
+    #[account(mut, constraint = registry_account.load_header()?.is_authority(authority.key))]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+}
+
+/// Accounts for the instruction to close a registry account or one of its pages
+#[derive(Accounts)]
+pub struct CloseRegistryAccount<'info> {
+    /// The authority of the registry account
+    pub authority: Signer<'info>,
+
+    /// The recipient of lamports
+    /// CHECK: any account can receive the lamports
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    /// The registry account of the authority
+    #[account(mut, constraint = registry_account.load_header()?.is_authority(authority.key))]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+
+    /// The page of the registry account being closed, if any
+    #[account(mut, constraint = registry_page.load_header()?.registry == registry_account.key())]
+    pub registry_page: Option<AccountLoader<'info, RegistryPage>>,
+}
+
+/// Accounts for the instruction to compact a registry account or one of its pages
+#[derive(Accounts)]
+pub struct CompactRegistry<'info> {
+    /// The authority of the registry account, or a delegate
+    pub authority: Signer<'info>,
+
+    /// The registry authority, which receives the rent that is no longer needed
+    /// CHECK: the account is the registry authority
+    #[account(mut, address = registry_account.load_header()?.authority)]
+    pub recipient: AccountInfo<'info>,
+
+    /// The registry account of the authority
+    #[account(mut, constraint = registry_account.load_header()?.can_sign(authority.key, permissions::REMOVE))]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+
+    /// The page of the registry account being compacted, if any
+    #[account(mut, constraint = registry_page.load_header()?.registry == registry_account.key())]
+    pub registry_page: Option<AccountLoader<'info, RegistryPage>>,
+}
+
+/// Accounts for the instruction to create a lookup table in the registry
+#[derive(Accounts)]
+pub struct CreateLookupTable<'info> {
+    /// The authority of the registry account, or a delegate
+    pub authority: Signer<'info>,
+
+    /// The payer of the transaction
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The registry account of the authority
+    #[account(mut, constraint = registry_account.load_header()?.can_sign(authority.key, permissions::CREATE))]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+
+    /// The page of the registry account that stores the lookup table, if any
+    #[account(mut, constraint = registry_page.load_header()?.registry == registry_account.key())]
+    pub registry_page: Option<AccountLoader<'info, RegistryPage>>,
+
+    /// The lookup table being created
+    /// CHECK: the account will be validated by the lookup table program
+    #[account(mut)]
+    pub lookup_table: AccountInfo<'info>,
+
+    /// CHECK: the account will be validated by the lookup table program
+    #[account(address = solana_address_lookup_table_program::ID)]
+    pub address_lookup_table_program: AccountInfo<'info>,
+
+    /// The system program
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for the instruction to create several lookup tables in the registry.
+/// The lookup tables being created are the remaining accounts.
+#[derive(Accounts)]
+pub struct CreateLookupTables<'info> {
+    /// The authority of the registry account, or a delegate
+    pub authority: Signer<'info>,
+
+    /// The payer of the transaction
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The registry account of the authority
+    #[account(mut, constraint = registry_account.load_header()?.can_sign(authority.key, permissions::CREATE))]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+
+    /// The page of the registry account that stores the lookup tables, if any
+    #[account(mut, constraint = registry_page.load_header()?.registry == registry_account.key())]
+    pub registry_page: Option<AccountLoader<'info, RegistryPage>>,
+
+    /// CHECK: the account will be validated by the lookup table program
+    #[account(address = solana_address_lookup_table_program::ID)]
+    pub address_lookup_table_program: AccountInfo<'info>,
+
+    /// The system program
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for the instruction to import a lookup table into the registry
+#[derive(Accounts)]
+pub struct ImportLookupTable<'info> {
+    /// The authority of the registry account
+    pub authority: Signer<'info>,
+
+    /// The payer of the transaction
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The registry account of the authority
+    #[account(mut, constraint = registry_account.load_header()?.is_authority(authority.key))]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+
+    /// The page of the registry account that stores the lookup table, if any
+    #[account(mut, constraint = registry_page.load_header()?.registry == registry_account.key())]
+    pub registry_page: Option<AccountLoader<'info, RegistryPage>>,
+
+    /// The lookup table being imported
+    /// CHECK: the account is deserialized as a lookup table
+    #[account(owner = solana_address_lookup_table_program::ID)]
+    pub lookup_table: AccountInfo<'info>,
+
+    /// The system program
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for the instruction to set the metadata of a lookup table
+#[derive(Accounts)]
+pub struct SetEntryMetadata<'info> {
+    /// The authority of the registry account, or a delegate
+    pub authority: Signer<'info>,
+
+    /// The registry account of the authority
+    #[account(mut, constraint = registry_account.load_header()?.can_sign(authority.key, permissions::CREATE))]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+
+    /// The page of the registry account that stores the lookup table, if any
+    #[account(mut, constraint = registry_page.load_header()?.registry == registry_account.key())]
+    pub registry_page: Option<AccountLoader<'info, RegistryPage>>,
+
+    /// The lookup table of the entry
+    /// CHECK: the account is only used to find the entry in the registry
+    pub lookup_table: AccountInfo<'info>,
+}
+
+/// Accounts for the instruction to append entries to a lookup table
+#[derive(Accounts)]
+pub struct AppendToLookupTable<'info> {
+    /// The authority of the registry account, or a delegate
+    pub authority: Signer<'info>,
+
+    /// The authority of the lookup table, if it is neither the registry account
+    /// nor the registry authority. This is the case for lookup tables created
+    /// before version 1 by a previous registry authority.
+    pub table_authority: Option<Signer<'info>>,
+
+    /// The payer of the transaction
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The registry account of the authority
+    #[account(constraint = registry_account.load_header()?.can_sign(authority.key, permissions::APPEND))]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+
+    /// The page of the registry account that stores the lookup table, if any
+    #[account(constraint = registry_page.load_header()?.registry == registry_account.key())]
+    pub registry_page: Option<AccountLoader<'info, RegistryPage>>,
+
+    /// The lookup table being created
+    /// CHECK: the account will be validated by the lookup table program
+    #[account(mut)]
+    pub lookup_table: AccountInfo<'info>,
+
+    /// CHECK: the account will be validated by the lookup table program
+    #[account(address = solana_address_lookup_table_program::ID)]
+    pub address_lookup_table_program: AccountInfo<'info>,
+
+    /// The system program
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for the instruction to remove a lookup table
+#[derive(Accounts)]
+pub struct RemoveLookupTable<'info> {
+    /// The authority of the registry account, or a delegate
+    pub authority: Signer<'info>,
+
+    /// The authority of the lookup table, if it is neither the registry account
+    /// nor the registry authority. This is the case for lookup tables created
+    /// before version 1 by a previous registry authority.
+    pub table_authority: Option<Signer<'info>>,
+
+    /// The recipient of lamports
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+
+    /// The registry account of the authority
+    #[account(mut, constraint = registry_account.load_header()?.can_sign(authority.key, permissions::REMOVE))]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+
+    /// The page of the registry account that stores the lookup table, if any
+    #[account(mut, constraint = registry_page.load_header()?.registry == registry_account.key())]
+    pub registry_page: Option<AccountLoader<'info, RegistryPage>>,
+
+    /// The lookup table being closed
+    /// CHECK: the account will be validated by the lookup table program
+    #[account(mut)]
+    pub lookup_table: AccountInfo<'info>,
+
+    /// CHECK: the account will be validated by the lookup table program
+    #[account(address = solana_address_lookup_table_program::ID)]
+    pub address_lookup_table_program: AccountInfo<'info>,
+
+    /// The system program
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for the instruction to freeze a lookup table
+#[derive(Accounts)]
+pub struct FreezeLookupTable<'info> {
+    /// The authority of the registry account
+    pub authority: Signer<'info>,
+
+    /// The authority of the lookup table, if it is neither the registry account
+    /// nor the registry authority. This is the case for lookup tables created
+    /// before version 1 by a previous registry authority.
+    pub table_authority: Option<Signer<'info>>,
+
+    /// The registry account of the authority
+    #[account(mut, constraint = registry_account.load_header()?.is_authority(authority.key))]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+
+    /// The page of the registry account that stores the lookup table, if any
+    #[account(mut, constraint = registry_page.load_header()?.registry == registry_account.key())]
+    pub registry_page: Option<AccountLoader<'info, RegistryPage>>,
+
+    /// The lookup table being frozen
+    /// CHECK: the account will be validated by the lookup table program
+    #[account(mut)]
+    pub lookup_table: AccountInfo<'info>,
+
+    /// CHECK: the account will be validated by the lookup table program
+    #[account(address = solana_address_lookup_table_program::ID)]
+    pub address_lookup_table_program: AccountInfo<'info>,
+}
+
+/// Accounts for the instruction to set the close recipient of a lookup table
+#[derive(Accounts)]
+pub struct SetAutoClose<'info> {
+    /// The authority of the registry account, or a delegate
+    pub authority: Signer<'info>,
+
+    /// The registry account of the authority
+    #[account(mut, constraint = registry_account.load_header()?.can_sign(authority.key, permissions::REMOVE))]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+
+    /// The page of the registry account that stores the lookup table, if any
+    #[account(mut, constraint = registry_page.load_header()?.registry == registry_account.key())]
+    pub registry_page: Option<AccountLoader<'info, RegistryPage>>,
+
+    /// The lookup table that closes automatically
+    /// CHECK: the account must be the lookup table of an entry in the registry
+    pub lookup_table: AccountInfo<'info>,
+}
+
+/// Accounts for the instruction to close a deactivated lookup table that has
+/// a close recipient
+#[derive(Accounts)]
+pub struct CloseDeactivatedTable<'info> {
+    /// The registry account that the lookup table belongs to
+    #[account(mut)]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+
+    /// The page of the registry account that stores the lookup table, if any
+    #[account(mut, constraint = registry_page.load_header()?.registry == registry_account.key())]
+    pub registry_page: Option<AccountLoader<'info, RegistryPage>>,
+
+    /// The lookup table being closed
+    /// CHECK: the account will be validated by the lookup table program
+    #[account(mut)]
+    pub lookup_table: AccountInfo<'info>,
+
+    /// The close recipient of the lookup table's entry
+    /// CHECK: the recipient must match the entry
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    /// CHECK: the account will be validated by the lookup table program
+    #[account(address = solana_address_lookup_table_program::ID)]
+    pub address_lookup_table_program: AccountInfo<'info>,
+}
+
+/// Accounts for the instruction to remove the entry of a lookup table that
+/// was closed outside the registry
+#[derive(Accounts)]
+pub struct PruneEntry<'info> {
+    /// The registry account that the lookup table belonged to
+    #[account(mut)]
+    pub registry_account: AccountLoader<'info, RegistryAccount>,
+
+    /// The page of the registry account that stores the lookup table, if any
+    #[account(mut, constraint = registry_page.load_header()?.registry == registry_account.key())]
+    pub registry_page: Option<AccountLoader<'info, RegistryPage>>,
+
+    /// The address of the lookup table
+    /// CHECK: the account must not be owned by the lookup table program, unless it has been closed
+    pub lookup_table: AccountInfo<'info>,
+}
+
 /// Errors used in the program
 #[error_code]
 pub enum ErrorCode {
@@ -417,8 +2217,8 @@ pub enum ErrorCode {
     #[msg("Invalid discriminator used")]
     InvalidDiscriminator = 10000,
 
-    /// The slot provided cannot be earlier than the last slot used
-    #[msg("Slot cannot be earlier than the last slot used")]
+    /// The slot provided must be later than the last slot used
+    #[msg("Slot must be later than the last slot used")]
     InvalidSlot,
 
     /// The lookup table provided is invalid
@@ -432,4 +2232,556 @@ pub enum ErrorCode {
     /// Thep rogram encountered some invalid state
     #[msg("The lookup registry is in an invalid state")]
     InvalidState,
+
+    /// The discriminator provided does not match the one stored in the registry
+    #[msg("The discriminator does not match the lookup table's discriminator")]
+    DiscriminatorMismatch,
+
+    /// The lookup table is frozen and cannot be modified
+    #[msg("The lookup table is frozen")]
+    TableFrozen,
+
+    /// The version of a registry account is not supported
+    #[msg("Unsupported registry version")]
+    InvalidVersion,
+
+    /// The registry still has lookup tables or pages
+    #[msg("The registry is not empty")]
+    RegistryNotEmpty,
+
+    /// The registry account has no space for more delegates
+    #[msg("There are too many delegates in the registry account")]
+    TooManyDelegates,
+
+    /// The delegate or its permissions are invalid
+    #[msg("Invalid delegate")]
+    InvalidDelegate,
+
+    /// A delegate tried to change a lookup table that the registry account is
+    /// not the authority of
+    #[msg("The registry account is not the authority of the lookup table")]
+    RegistryNotTableAuthority,
+
+    /// The lookup table is already in the registry
+    #[msg("The lookup table is already in the registry")]
+    DuplicateLookupTable,
+
+    /// The namespace of a registry is too long
+    #[msg("The namespace is too long")]
+    InvalidNamespace,
+
+    /// The label of an entry is too long
+    #[msg("The label is too long")]
+    InvalidLabel,
+
+    /// More addresses were given than a lookup table can hold
+    #[msg("There are too many addresses for the lookup table")]
+    TooManyAddresses,
+
+    /// An address being appended is already in the lookup table
+    #[msg("The address is already in the lookup table")]
+    DuplicateAddress,
+
+    /// A deactivated lookup table can't be closed before the earliest slot
+    /// when its deactivation slot can no longer be a recent slot
+    #[msg("The lookup table is still cooling down after being deactivated")]
+    TableStillCoolingDown,
+
+    /// The signers or threshold of a multisig are invalid
+    #[msg("Invalid multisig signers or threshold")]
+    InvalidMultisig,
+
+    /// Not enough signers of the multisig have signed
+    #[msg("The multisig threshold has not been met")]
+    MultisigThresholdNotMet,
+
+    /// Only the authority can close a lookup table that has no close recipient
+    #[msg("The lookup table does not close automatically")]
+    AutoCloseDisabled,
+
+    /// The recipient is not the close recipient of the lookup table
+    #[msg("Invalid recipient of the lookup table's lamports")]
+    InvalidRecipient,
+
+    /// Only entries of lookup tables that no longer exist can be pruned
+    #[msg("The lookup table still exists")]
+    LookupTableExists,
+}
+
+#[cfg(all(test, feature = "program"))]
+mod tests {
+    use super::*;
+
+    /// A registry account without entries or pages
+    fn empty_registry() -> RegistryAccount {
+        bytemuck::Zeroable::zeroed()
+    }
+
+    #[test]
+    fn registry_pages_are_numbered_up_to_max_registry_pages() {
+        let mut registry = empty_registry();
+        for page in 1..=MAX_REGISTRY_PAGES {
+            registry.pages = registry.next_page().unwrap();
+            assert_eq!(registry.pages as usize, page);
+        }
+        assert_eq!(
+            registry.next_page().unwrap_err(),
+            ErrorCode::TooManyEntries.into()
+        );
+    }
+
+    #[test]
+    fn lookup_tables_are_created_with_later_slots() {
+        let mut registry = empty_registry();
+        registry.last_created_slot = 10;
+        for slot in [9, 10] {
+            assert_eq!(
+                set_last_created_slot(&mut registry, slot).unwrap_err(),
+                ErrorCode::InvalidSlot.into()
+            );
+        }
+        set_last_created_slot(&mut registry, 11).unwrap();
+        assert_eq!(registry.last_created_slot, 11);
+        assert!(set_last_created_slot(&mut registry, 11).is_err());
+    }
+
+    #[test]
+    fn lookup_tables_are_created_for_each_remaining_account() {
+        let tables = [11, 12].map(|recent_slot| NewLookupTable {
+            recent_slot,
+            discriminator: 2,
+        });
+        let keys = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let mut lamports = [0; 3];
+        let mut data = [[0u8; 0]; 3];
+        let accounts = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((key, lamports), data)| {
+                AccountInfo::new(key, false, true, lamports, data, &crate::ID, false, 0)
+            })
+            .collect::<Vec<_>>();
+
+        let (lookup_tables, cosigners) =
+            new_lookup_table_accounts(&tables, &accounts[..2]).unwrap();
+        assert_eq!(lookup_tables[0].key, &keys[0]);
+        assert_eq!(lookup_tables[1].key, &keys[1]);
+        assert!(cosigners.is_empty());
+        // The cosigners of a multisig follow the lookup tables
+        let (lookup_tables, cosigners) = new_lookup_table_accounts(&tables, &accounts).unwrap();
+        assert_eq!(lookup_tables.len(), 2);
+        assert_eq!(cosigners[0].key, &keys[2]);
+        assert_eq!(
+            new_lookup_table_accounts(&tables, &accounts[..1]).unwrap_err(),
+            ErrorCode::InvalidLookupTable.into()
+        );
+        assert!(new_lookup_table_accounts(&[], &[]).is_err());
+
+        // Each lookup table must have a later slot than the one before it
+        let mut registry = empty_registry();
+        for table in &tables {
+            set_last_created_slot(&mut registry, table.recent_slot).unwrap();
+        }
+        assert_eq!(registry.last_created_slot, 12);
+        assert!(set_last_created_slot(&mut registry, tables[1].recent_slot).is_err());
+    }
+
+    #[test]
+    fn lookup_tables_are_extended_with_at_most_their_capacity() {
+        use solana_address_lookup_table_program::state::LOOKUP_TABLE_MAX_ADDRESSES;
+
+        check_address_count(0).unwrap();
+        check_address_count(LOOKUP_TABLE_MAX_ADDRESSES).unwrap();
+        assert_eq!(
+            check_address_count(LOOKUP_TABLE_MAX_ADDRESSES + 1).unwrap_err(),
+            ErrorCode::TooManyAddresses.into()
+        );
+    }
+
+    #[test]
+    fn registries_are_compacted_without_empty_entries() {
+        let entry = |discriminator| RegistryEntry {
+            discriminator,
+            table: Pubkey::new_unique(),
+            metadata: EntryMetadata::default(),
+            deactivation_slot: 0,
+            close_recipient: Pubkey::default(),
+        };
+        let mut registry = empty_registry();
+        let mut tables = vec![
+            entry(2),
+            entry(discriminator::EMPTY),
+            entry(3),
+            entry(discriminator::EMPTY),
+            entry(4),
+        ];
+        registry.len = 3;
+        registry.capacity = 5;
+        let kept = [0, 2, 4].map(|i| tables[i].table);
+
+        // The entries after an empty entry move forward, and the empty entries
+        // that are left at the end are removed from the account
+        let removed = compact_entries(&mut registry, &mut tables).unwrap();
+        assert_eq!(removed, 2);
+        tables.truncate(tables.len() - removed);
+        let compacted = tables.iter().map(|e| e.table).collect::<Vec<_>>();
+        assert_eq!(compacted, kept);
+        assert_eq!((registry.len, registry.capacity), (3, 3));
+        assert_eq!(compact_entries(&mut registry, &mut tables).unwrap(), 0);
+
+        // The rent above the balance needed for the new size is refunded
+        let (key, recipient_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut lamports, mut recipient_lamports) = (10_000, 500);
+        let (mut data, mut recipient_data) = ([0u8; 0], [0u8; 0]);
+        let account = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            &mut data,
+            &crate::ID,
+            false,
+            0,
+        );
+        let recipient = AccountInfo::new(
+            &recipient_key,
+            false,
+            true,
+            &mut recipient_lamports,
+            &mut recipient_data,
+            &crate::ID,
+            false,
+            0,
+        );
+        refund_excess_rent(&account, &recipient, 7_000).unwrap();
+        assert_eq!(account.lamports(), 7_000);
+        assert_eq!(recipient.lamports(), 3_500);
+    }
+
+    #[test]
+    fn lookup_tables_are_closed_once_cooled_down() {
+        let mut entry = RegistryEntry {
+            discriminator: 2,
+            table: Pubkey::new_unique(),
+            metadata: EntryMetadata::default(),
+            deactivation_slot: 0,
+            close_recipient: Pubkey::default(),
+        };
+        assert_eq!(entry.closeable_at_slot(), None);
+
+        // The deactivation slot of tables deactivated before the registry was
+        // migrated is unknown, and the lookup table program checks them
+        entry.discriminator = discriminator::DEACTIVATED;
+        assert_eq!(entry.closeable_at_slot(), None);
+        check_cooled_down(&entry, 0).unwrap();
+
+        entry.deactivation_slot = 1000;
+        let closeable_at_slot = entry.closeable_at_slot().unwrap();
+        assert_eq!(closeable_at_slot, 1000 + 512 + 1);
+        assert_eq!(
+            check_cooled_down(&entry, closeable_at_slot - 1).unwrap_err(),
+            ErrorCode::TableStillCoolingDown.into()
+        );
+        check_cooled_down(&entry, closeable_at_slot).unwrap();
+    }
+
+    #[test]
+    fn registries_are_closed_once_empty() {
+        let mut registry = empty_registry();
+        let mut page: RegistryPage = bytemuck::Zeroable::zeroed();
+        registry.len = 1;
+        registry.pages = 2;
+        page.page = 2;
+        page.len = 1;
+
+        // The registry and the page still have lookup tables
+        let not_empty: Error = ErrorCode::RegistryNotEmpty.into();
+        assert_eq!(check_registry_closeable(&registry).unwrap_err(), not_empty);
+        assert_eq!(
+            check_page_closeable(&registry, &page).unwrap_err(),
+            not_empty
+        );
+
+        // Pages are closed from the last one
+        page.len = 0;
+        page.page = 1;
+        assert_eq!(
+            check_page_closeable(&registry, &page).unwrap_err(),
+            not_empty
+        );
+        page.page = 2;
+        check_page_closeable(&registry, &page).unwrap();
+
+        // The registry is closed once it has no lookup tables and no pages
+        registry.len = 0;
+        assert_eq!(check_registry_closeable(&registry).unwrap_err(), not_empty);
+        registry.pages = 0;
+        check_registry_closeable(&registry).unwrap();
+    }
+
+    #[test]
+    fn delegates_only_change_lookup_tables_of_the_registry() {
+        let mut registry = empty_registry();
+        let registry_address = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        registry.authority = Pubkey::new_unique();
+        registry.delegates[0] = Delegate {
+            key: delegate,
+            permissions: permissions::APPEND,
+            reserved0: [0; 7],
+        };
+        assert!(registry.can_sign(&delegate, permissions::APPEND));
+        assert!(!registry.can_sign(&delegate, permissions::REMOVE));
+
+        // A delegate signs for the lookup tables of the registry without the authority
+        let registry_owned = Some(registry_address);
+        for signer in [registry.authority, delegate] {
+            assert!(registry_is_table_authority(
+                &registry,
+                &registry_address,
+                registry_owned,
+                &signer
+            )
+            .unwrap());
+        }
+
+        // Only the authority changes lookup tables that a key is the authority of
+        let key_owned = Some(registry.authority);
+        assert!(!registry_is_table_authority(
+            &registry,
+            &registry_address,
+            key_owned,
+            &registry.authority
+        )
+        .unwrap());
+        assert_eq!(
+            registry_is_table_authority(&registry, &registry_address, key_owned, &delegate)
+                .unwrap_err(),
+            ErrorCode::RegistryNotTableAuthority.into()
+        );
+    }
+
+    #[test]
+    fn registry_signs_for_its_lookup_tables() {
+        use solana_address_lookup_table_program::state::{AddressLookupTable, LookupTableMeta};
+
+        fn account_info<'a>(
+            key: &'a Pubkey,
+            is_signer: bool,
+            lamports: &'a mut u64,
+            data: &'a mut [u8],
+        ) -> AccountInfo<'a> {
+            AccountInfo::new(key, is_signer, true, lamports, data, &crate::ID, false, 0)
+        }
+        fn lookup_table_data(authority: Pubkey) -> Vec<u8> {
+            AddressLookupTable {
+                meta: LookupTableMeta::new(authority),
+                addresses: Default::default(),
+            }
+            .serialize_for_tests()
+            .unwrap()
+        }
+
+        let (registry_address, authority_key, other_key, table_key) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (mut registry_lamports, mut authority_lamports, mut other_lamports) = (0, 0, 0);
+        // Zero-copy accounts are read from aligned data
+        let header = RegistryAccount {
+            version: REGISTRY_VERSION,
+            authority: authority_key,
+            ..empty_registry()
+        };
+        let mut registry_words = vec![0u64; 1 + std::mem::size_of::<RegistryAccount>() / 8];
+        let registry_data: &mut [u8] = bytemuck::cast_slice_mut(&mut registry_words);
+        registry_data[..8]
+            .copy_from_slice(&<RegistryAccount as anchor_lang::Discriminator>::DISCRIMINATOR);
+        registry_data[8..].copy_from_slice(bytemuck::bytes_of(&header));
+        let registry_info = account_info(
+            &registry_address,
+            false,
+            &mut registry_lamports,
+            registry_data,
+        );
+        let registry = AccountLoader::<RegistryAccount>::try_from(&registry_info).unwrap();
+        let authority_info = account_info(&authority_key, true, &mut authority_lamports, &mut []);
+        let authority = Signer::try_from(&authority_info).unwrap();
+        let other_info = account_info(&other_key, true, &mut other_lamports, &mut []);
+        let other = Some(Signer::try_from(&other_info).unwrap());
+
+        // The program signs for the registry account with its seeds
+        let (mut table_lamports, mut table_data) = (0, lookup_table_data(registry_address));
+        let table = account_info(&table_key, false, &mut table_lamports, &mut table_data);
+        for table_authority_signer in [None, other.clone()] {
+            let signer =
+                table_authority(&registry, &table, &authority, &table_authority_signer).unwrap();
+            assert!(signer.registry_signs);
+            assert_eq!(signer.info.key, &registry_address);
+        }
+
+        // Other lookup tables are signed for by their authority
+        let (mut table_lamports, mut table_data) = (0, lookup_table_data(authority_key));
+        let table = account_info(&table_key, false, &mut table_lamports, &mut table_data);
+        let signer = table_authority(&registry, &table, &authority, &None).unwrap();
+        assert!(!signer.registry_signs);
+        assert_eq!(signer.info.key, &authority_key);
+        let signer = table_authority(&registry, &table, &authority, &other).unwrap();
+        assert!(!signer.registry_signs);
+        assert_eq!(signer.info.key, &other_key);
+    }
+
+    #[test]
+    fn lookup_tables_of_the_registry_or_its_authority_are_imported() {
+        use solana_address_lookup_table_program::state::LookupTableMeta;
+
+        let mut registry = empty_registry();
+        let registry_address = Pubkey::new_unique();
+        registry.authority = Pubkey::new_unique();
+
+        for authority in [registry_address, registry.authority] {
+            check_importable(
+                &registry,
+                &registry_address,
+                &LookupTableMeta::new(authority),
+            )
+            .unwrap();
+        }
+
+        let invalid: Error = ErrorCode::InvalidLookupTable.into();
+        let other = LookupTableMeta::new(Pubkey::new_unique());
+        assert_eq!(
+            check_importable(&registry, &registry_address, &other).unwrap_err(),
+            invalid
+        );
+        let frozen = LookupTableMeta::default();
+        assert_eq!(
+            check_importable(&registry, &registry_address, &frozen).unwrap_err(),
+            invalid
+        );
+        let deactivated = LookupTableMeta {
+            deactivation_slot: 10,
+            ..LookupTableMeta::new(registry.authority)
+        };
+        assert_eq!(
+            check_importable(&registry, &registry_address, &deactivated).unwrap_err(),
+            invalid
+        );
+    }
+
+    #[test]
+    fn registry_seeds_derive_the_namespaced_address() {
+        let authority = Pubkey::new_unique();
+        let mut registry = empty_registry();
+        registry.seed_authority = authority;
+
+        for namespace in [&b""[..], b"market", &[7; MAX_NAMESPACE_LEN]] {
+            let (address, bump) =
+                Pubkey::find_program_address(&[authority.as_ref(), namespace], &crate::ID);
+            registry.namespace = [0; MAX_NAMESPACE_LEN];
+            registry.namespace[..namespace.len()].copy_from_slice(namespace);
+            registry.namespace_len = namespace.len() as u8;
+            registry.seed = [bump];
+            assert_eq!(registry.namespace(), namespace);
+            assert_eq!(
+                Pubkey::create_program_address(&registry.signer_seeds(), &crate::ID).unwrap(),
+                address
+            );
+        }
+
+        // The empty namespace is the registry without a namespace
+        let (address, _) = Pubkey::find_program_address(&[authority.as_ref()], &crate::ID);
+        let (namespaced, _) = Pubkey::find_program_address(&[authority.as_ref(), b""], &crate::ID);
+        assert_eq!(address, namespaced);
+    }
+
+    #[test]
+    fn entry_labels_are_read_without_padding() {
+        let mut metadata = EntryMetadata::default();
+        assert_eq!(metadata.label(), b"");
+
+        metadata.label[..6].copy_from_slice(b"oracle");
+        assert_eq!(metadata.label(), b"oracle");
+
+        metadata.label = [b'a'; MAX_LABEL_LEN];
+        assert_eq!(metadata.label(), &[b'a'; MAX_LABEL_LEN]);
+    }
+
+    #[test]
+    fn filter_duplicates_keeps_order_of_new_addresses() {
+        let existing = (0..256).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+        let new = (0..4).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+        let addresses = vec![new[2], existing[10], new[0], new[2], existing[255], new[1]];
+
+        let filtered = filter_duplicates(&existing, addresses.clone(), true).unwrap();
+        assert_eq!(filtered, vec![new[2], new[0], new[1]]);
+
+        assert!(filter_duplicates(&existing, addresses, false).is_err());
+        assert_eq!(
+            filter_duplicates(&existing, new.clone(), false).unwrap(),
+            new
+        );
+    }
+
+    #[test]
+    fn check_authority_needs_multisig_threshold() {
+        let signers = (0..3).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+        let delegate = Pubkey::new_unique();
+        let mut registry = RegistryAccount {
+            authority: Pubkey::new_unique(),
+            ..empty_registry()
+        };
+        registry.multisig.signers[..3].copy_from_slice(&signers);
+        registry.multisig.threshold = 2;
+        registry.delegates[0] = Delegate {
+            key: delegate,
+            permissions: permissions::APPEND,
+            reserved0: [0; 7],
+        };
+
+        let owner = Pubkey::default();
+        let (mut lamports, mut data) = ([0; 2], [[0u8; 0]; 2]);
+        let [lamports0, lamports1] = &mut lamports;
+        let [data0, data1] = &mut data;
+        let cosigners = [
+            AccountInfo::new(&signers[1], true, false, lamports0, data0, &owner, false, 0),
+            AccountInfo::new(
+                &signers[2],
+                false,
+                false,
+                lamports1,
+                data1,
+                &owner,
+                false,
+                0,
+            ),
+        ];
+        let (signed, unsigned) = cosigners.split_at(1);
+
+        // The authority can't sign alone in multisig mode
+        assert!(registry.can_sign(&signers[0], 0));
+        assert!(!registry.can_sign(&registry.authority, 0));
+        assert!(check_authority(&registry, &registry.authority, &[], None).is_err());
+        assert!(check_authority(&registry, &signers[0], &[], None).is_err());
+        assert!(check_authority(&registry, &signers[0], unsigned, None).is_err());
+        assert!(check_authority(&registry, &signers[0], signed, None).is_ok());
+        // The same signer doesn't count twice
+        assert!(check_authority(&registry, &signers[1], signed, None).is_err());
+        // Delegates sign alone for their permissions
+        assert!(check_authority(&registry, &delegate, &[], Some(permissions::APPEND)).is_ok());
+        assert!(check_authority(&registry, &delegate, &[], Some(permissions::CREATE)).is_err());
+        assert!(check_authority(&registry, &delegate, &[], None).is_err());
+
+        // Without a multisig, the authority signs alone
+        registry.multisig = Multisig::default();
+        assert!(check_authority(&registry, &registry.authority, &[], None).is_ok());
+        assert!(check_authority(&registry, &signers[0], signed, None).is_err());
+    }
 }
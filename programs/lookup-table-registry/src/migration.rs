@@ -0,0 +1,350 @@
+//! Decoding and migration of registry accounts created with older layouts.
+//!
+//! The layout of a registry account is determined by its version, which is at
+//! the same offset in all versions.
+//!
+//! - 0: the authority, the counters and the last created slot
+//! - 1: adds `seed_authority`, `namespace`, `pending_authority`, `delegates`
+//!   and `multisig`, with the length of the namespace in a previously
+//!   reserved byte. Entries grow from [LEGACY_ENTRY_SIZE] bytes to add their
+//!   metadata, deactivation slot and close recipient. The header has a fixed size and is followed by the
+//!   entries, without their length, so that accounts can be accessed without
+//!   copying them.
+//!
+//! Accounts of version 0 are serialized with borsh, and the entries are
+//! preceded by their length. The header of version 1 keeps the fields of
+//! version 0 at the same offsets. Entries only move towards the end of the
+//! account when it is migrated.
+
+use anchor_lang::{prelude::*, Discriminator};
+
+use crate::{
+    Decoded, EntryMetadata, RegistryAccount, RegistryEntry, RegistryHeader, RegistryPage,
+    MAX_NAMESPACE_LEN, REGISTRY_ENTRY_SIZE, REGISTRY_VERSION,
+};
+
+/// The size of an entry of version 0, which has no metadata
+pub const LEGACY_ENTRY_SIZE: usize = 40;
+
+impl RegistryAccount {
+    /// Deserialize a registry account of any version into the current layout,
+    /// together with its entries. The version of the account is kept.
+    pub fn try_deserialize_any_version(data: &[u8]) -> Result<Decoded<Self>> {
+        let (registry, count, entries) = decode_registry_header(data)?;
+        let tables = decode_entries(entries, count, registry.version)?;
+        Ok(Decoded {
+            header: registry,
+            tables,
+        })
+    }
+}
+
+impl RegistryPage {
+    /// Deserialize a page together with its entries. Pages have only been
+    /// created with the current layout, so they are never migrated.
+    pub fn try_deserialize_with_tables(data: &[u8]) -> Result<Decoded<Self>> {
+        check_discriminator(data, &RegistryPage::DISCRIMINATOR)?;
+        let (page, count, entries) =
+            decode_current_header(data)?.ok_or(ErrorCode::AccountDidNotDeserialize)?;
+        let tables = decode_entries(entries, count, REGISTRY_VERSION)?;
+        Ok(Decoded {
+            header: page,
+            tables,
+        })
+    }
+}
+
+/// The data length that a registry account needs once it is migrated to the
+/// current version, or none if it already has the current version.
+pub fn registry_migration_len(data: &[u8]) -> Result<Option<usize>> {
+    let (registry, count, _) = decode_registry_header(data)?;
+    Ok((registry.version < REGISTRY_VERSION)
+        .then(|| 8 + std::mem::size_of::<RegistryAccount>() + count * REGISTRY_ENTRY_SIZE))
+}
+
+/// Migrate a registry account to the current version in place.
+/// The data must be at least as long as [registry_migration_len].
+///
+/// The number of populated entries is counted again, as registries of version
+/// 0 did not count the entries that reused an empty entry.
+pub fn migrate_registry_data(data: &mut [u8]) -> Result<()> {
+    let (mut registry, count, entries_offset, len) = {
+        let (registry, count, entries) = decode_registry_header(data)?;
+        let len = count_populated_entries(entries, count, entry_size(registry.version))?;
+        (registry, count, data.len() - entries.len(), len)
+    };
+    let from_entry_size = entry_size(registry.version);
+    registry.version = REGISTRY_VERSION;
+    registry.len = len;
+    registry.capacity = count as u8;
+
+    expand_entries(data, entries_offset, count, from_entry_size, &registry)
+}
+
+/// Decode the header of a registry account of any version into the current
+/// layout, with the number of entries and the data that follows the header.
+pub(crate) fn decode_registry_header(data: &[u8]) -> Result<(RegistryAccount, usize, &[u8])> {
+    check_discriminator(data, &RegistryAccount::DISCRIMINATOR)?;
+    if let Some(decoded) = decode_current_header(data)? {
+        return Ok(decoded);
+    }
+    let mut data = &data[8..];
+    let authority: Pubkey = read(&mut data)?;
+    let version: u8 = read(&mut data)?;
+    // The data of the current version is too short for its header
+    if version >= REGISTRY_VERSION {
+        return err!(crate::ErrorCode::InvalidVersion);
+    }
+    let seed = read(&mut data)?;
+    let len = read(&mut data)?;
+    let capacity = read(&mut data)?;
+    let pages = read(&mut data)?;
+    // Reserved in version 0, when it was always 0
+    let namespace_len = read(&mut data)?;
+    let reserved0 = read(&mut data)?;
+    let last_created_slot = read(&mut data)?;
+    let count: u32 = read(&mut data)?;
+
+    let registry = RegistryAccount {
+        authority,
+        version,
+        seed,
+        len,
+        capacity,
+        pages,
+        namespace_len,
+        reserved0,
+        last_created_slot,
+        // The address of a registry of version 0 is derived from its authority
+        seed_authority: authority,
+        namespace: [0; MAX_NAMESPACE_LEN],
+        pending_authority: Pubkey::default(),
+        delegates: Default::default(),
+        multisig: Default::default(),
+        reserved1: [0; 8],
+    };
+    Ok((registry, count as usize, data))
+}
+
+/// Decode the header of an account that has the current layout, with the
+/// number of entries and the data that follows the header. Returns none if
+/// the account has an older layout, and errors if it has a newer one.
+///
+/// The version is at the same offset in all layouts, and the data doesn't
+/// need to be aligned.
+fn decode_current_header<T: RegistryHeader + bytemuck::Pod>(
+    data: &[u8],
+) -> Result<Option<(T, usize, &[u8])>> {
+    let Some(header) = data.get(8..8 + std::mem::size_of::<T>()) else {
+        return Ok(None);
+    };
+    let header: T = bytemuck::pod_read_unaligned(header);
+    match header.version() {
+        REGISTRY_VERSION => {
+            let count = header.capacity() as usize;
+            Ok(Some((header, count, &data[8 + std::mem::size_of::<T>()..])))
+        }
+        version if version > REGISTRY_VERSION => err!(crate::ErrorCode::InvalidVersion),
+        _ => Ok(None),
+    }
+}
+
+/// The size of an entry in the layout of a version
+fn entry_size(version: u8) -> usize {
+    if version == 0 {
+        LEGACY_ENTRY_SIZE
+    } else {
+        REGISTRY_ENTRY_SIZE
+    }
+}
+
+fn decode_entries(data: &[u8], count: usize, version: u8) -> Result<Vec<RegistryEntry>> {
+    let entry_size = entry_size(version);
+    let data = data
+        .get(..count * entry_size)
+        .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+    data.chunks_exact(entry_size)
+        .map(|mut entry| {
+            if version == 0 {
+                Ok(RegistryEntry {
+                    discriminator: read(&mut entry)?,
+                    table: read(&mut entry)?,
+                    metadata: EntryMetadata::default(),
+                    deactivation_slot: 0,
+                    close_recipient: Pubkey::default(),
+                })
+            } else {
+                Ok(bytemuck::pod_read_unaligned(entry))
+            }
+        })
+        .collect()
+}
+
+/// The number of entries that are not empty
+fn count_populated_entries(data: &[u8], count: usize, entry_size: usize) -> Result<u8> {
+    let data = data
+        .get(..count * entry_size)
+        .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+    let len = data
+        .chunks_exact(entry_size)
+        .filter(|entry| entry[..8] != crate::discriminator::EMPTY.to_le_bytes())
+        .count();
+    u8::try_from(len).map_err(|_| error!(crate::ErrorCode::InvalidState))
+}
+
+/// Move the entries of an older layout to their offset in the current layout,
+/// and write the header of the current layout before them. Entries that grow
+/// have their new fields zeroed, which is the default metadata and an unknown
+/// deactivation slot.
+fn expand_entries<T: bytemuck::Pod>(
+    data: &mut [u8],
+    from: usize,
+    count: usize,
+    from_entry_size: usize,
+    header: &T,
+) -> Result<()> {
+    let to = 8 + std::mem::size_of::<T>();
+    if from > to
+        || from_entry_size > REGISTRY_ENTRY_SIZE
+        || to + count * REGISTRY_ENTRY_SIZE > data.len()
+    {
+        return err!(ErrorCode::AccountDidNotSerialize);
+    }
+    // Entries move towards the end of the account, so the last entry is moved
+    // first to not overwrite the entries that have not moved yet
+    for i in (0..count).rev() {
+        let source = from + i * from_entry_size;
+        let target = to + i * REGISTRY_ENTRY_SIZE;
+        data.copy_within(source..source + from_entry_size, target);
+        // The metadata, deactivation slot and close recipient are empty
+        data[target + from_entry_size..target + REGISTRY_ENTRY_SIZE].fill(0);
+    }
+    data[8..to].copy_from_slice(bytemuck::bytes_of(header));
+    Ok(())
+}
+
+fn check_discriminator(data: &[u8], discriminator: &[u8; 8]) -> Result<()> {
+    if data.len() < 8 || &data[..8] != discriminator {
+        return err!(ErrorCode::AccountDiscriminatorMismatch);
+    }
+    Ok(())
+}
+
+fn read<T: AnchorDeserialize>(data: &mut &[u8]) -> Result<T> {
+    T::deserialize(data).map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::{Owner, ZeroCopy};
+
+    use super::*;
+    use crate::{discriminator, Delegate, LoadRegistry, MAX_DELEGATES};
+
+    /// An entry of version 0
+    #[derive(AnchorSerialize)]
+    struct LegacyEntry {
+        discriminator: u64,
+        table: Pubkey,
+    }
+
+    /// A registry account of version 0
+    #[derive(AnchorSerialize)]
+    struct RegistryAccountV0 {
+        authority: Pubkey,
+        version: u8,
+        seed: [u8; 1],
+        len: u8,
+        capacity: u8,
+        pages: u8,
+        reserved0: [u8; 3],
+        last_created_slot: u64,
+        tables: Vec<LegacyEntry>,
+    }
+
+    fn entries() -> Vec<LegacyEntry> {
+        (0..5)
+            .map(|i| LegacyEntry {
+                discriminator: if i == 3 { discriminator::EMPTY } else { 2 + i },
+                table: Pubkey::new_unique(),
+            })
+            .collect()
+    }
+
+    /// Load migrated data without copying it, as the program does
+    fn load<T: RegistryHeader + ZeroCopy + Owner>(
+        data: &[u8],
+        check: impl FnOnce(&T, &[RegistryEntry]),
+    ) {
+        // Account data is aligned in the program
+        let mut aligned = vec![0u64; (data.len() + 7) / 8];
+        let aligned = &mut bytemuck::cast_slice_mut(&mut aligned)[..data.len()];
+        aligned.copy_from_slice(data);
+        let (key, owner, mut lamports) = (Pubkey::new_unique(), T::owner(), 0);
+        let info = AccountInfo::new(&key, false, true, &mut lamports, aligned, &owner, false, 0);
+        let loader = AccountLoader::<T>::try_from(&info).unwrap();
+        let (header, tables) = loader.load_tables_mut().unwrap();
+        check(&header, &tables);
+    }
+
+    fn assert_entries_match(entries: &[RegistryEntry], expected: &[LegacyEntry]) {
+        assert_eq!(entries.len(), expected.len());
+        for (entry, expected) in entries.iter().zip(expected) {
+            assert_eq!(entry.discriminator, expected.discriminator);
+            assert_eq!(entry.table, expected.table);
+            assert_eq!(entry.metadata, EntryMetadata::default());
+            assert_eq!(entry.deactivation_slot, 0);
+            assert!(!entry.is_auto_close());
+            assert_eq!(entry.closeable_at_slot(), None);
+        }
+    }
+
+    #[test]
+    fn migrate_registry_from_version_0() {
+        // Version 0 did not count the entries that reused an empty entry
+        let legacy = RegistryAccountV0 {
+            authority: Pubkey::new_unique(),
+            version: 0,
+            seed: [254],
+            len: 1,
+            capacity: 5,
+            pages: 0,
+            reserved0: [0; 3],
+            last_created_slot: 1234,
+            tables: entries(),
+        };
+        let mut data = RegistryAccount::DISCRIMINATOR.to_vec();
+        legacy.serialize(&mut data).unwrap();
+
+        // The legacy layout can be read before the account is migrated
+        let decoded = RegistryAccount::try_deserialize_any_version(&data).unwrap();
+        assert_eq!(decoded.version, 0);
+        assert_entries_match(&decoded.tables, &legacy.tables);
+
+        let migrated_len = registry_migration_len(&data).unwrap().unwrap();
+        data.resize(migrated_len, 0);
+        migrate_registry_data(&mut data).unwrap();
+        assert_eq!(registry_migration_len(&data).unwrap(), None);
+
+        let registry = RegistryAccount::try_deserialize_any_version(&data).unwrap();
+        assert_eq!(registry.authority, legacy.authority);
+        assert_eq!(registry.version, REGISTRY_VERSION);
+        assert_eq!(registry.seed, legacy.seed);
+        assert_eq!(registry.len, 4);
+        assert_eq!(registry.capacity, legacy.capacity);
+        assert_eq!(registry.pages, legacy.pages);
+        assert_eq!(registry.last_created_slot, legacy.last_created_slot);
+        // The address of a registry of version 0 is derived from its authority
+        assert_eq!(registry.seed_authority, legacy.authority);
+        assert_eq!(registry.pending_authority, Pubkey::default());
+        assert_eq!(registry.namespace(), b"");
+        assert_eq!(registry.delegates, [Delegate::default(); MAX_DELEGATES]);
+        assert_eq!(registry.multisig, Default::default());
+        assert_entries_match(&registry.tables, &legacy.tables);
+        load::<RegistryAccount>(&data, |header, tables| {
+            assert_eq!(header.authority, legacy.authority);
+            assert_eq!(header.capacity, legacy.capacity);
+            assert_entries_match(tables, &legacy.tables);
+        });
+    }
+}
@@ -1,21 +1,57 @@
-use anchor_lang::prelude::*;
+use std::cell::{Ref, RefMut};
+
+use anchor_lang::{prelude::*, ZeroCopy};
 
 // TODO: we can leave this as unlimited
 pub const REGISTRY_ENTRY_SIZE: usize = std::mem::size_of::<RegistryEntry>();
-/// The maximum number of registry entries.
+/// The maximum number of registry entries in a registry account or in one of its pages.
 ///
 /// Note that this size can be increased, however a practical limit of u8::MAX
 /// has been selected on a reasonable assumption that 255 entries are sufficient.
 /// Each lookup table can store up to 256 accounts, thus a registry can have 65k records.
-pub const MAX_REGISTRY_ENTRIES: usize =
-    (10240 - std::mem::size_of::<RegistryAccount>()) / REGISTRY_ENTRY_SIZE;
+/// Registries that need more entries can add overflow pages, see [RegistryPage].
+///
+/// The limit doesn't depend on the size of an entry. Accounts are created with
+/// only their header and grow as entries are added, so they can grow past the
+/// size that an account can be created with over several instructions.
+pub const MAX_REGISTRY_ENTRIES: usize = 254;
+
+/// The maximum number of overflow pages that a registry can have
+pub const MAX_REGISTRY_PAGES: usize = u8::MAX as usize;
+
+/// The maximum number of delegates that can sign for a registry authority
+pub const MAX_DELEGATES: usize = 4;
+
+/// The maximum number of signers of a multisig authority
+pub const MAX_MULTISIG_SIGNERS: usize = 5;
+
+/// The maximum length of the namespace of a registry, which is the maximum
+/// length of a seed
+pub const MAX_NAMESPACE_LEN: usize = 32;
+
+/// The maximum length of the label of an entry
+pub const MAX_LABEL_LEN: usize = 32;
 
-/// Current format allows up to 254 lookup accounts
-const _: () = assert!(MAX_REGISTRY_ENTRIES == 254);
 const _: () = assert!(MAX_REGISTRY_ENTRIES < u8::MAX as usize);
+/// A page must be able to hold as many entries as a registry account
+const _: () =
+    assert!(std::mem::size_of::<RegistryPage>() <= std::mem::size_of::<RegistryAccount>());
+
+/// The version of newly created registry accounts
+pub const REGISTRY_VERSION: u8 = 1;
+
+/// Entries follow the header, and must stay aligned for zero-copy access
+const _: () = assert!(
+    (8 + std::mem::size_of::<RegistryAccount>()) % std::mem::align_of::<RegistryEntry>() == 0
+);
+const _: () =
+    assert!((8 + std::mem::size_of::<RegistryPage>()) % std::mem::align_of::<RegistryEntry>() == 0);
 
 /// A registry account that stores the lookup tables that an authority has created.
-#[account]
+///
+/// The account is accessed without copying it, and its entries follow this
+/// header in the account data, see [LoadRegistry].
+#[account(zero_copy)]
 #[repr(C)]
 #[derive(Debug)]
 pub struct RegistryAccount {
@@ -24,6 +60,21 @@ pub struct RegistryAccount {
     /// The version of the registry account. The version denotes some change in
     /// functionality.
     /// - 0: initial version with no discriminators
+    /// - 1: discriminators are chosen by the creator of a lookup table.
+    ///   Entries created in version 0 have a discriminator of 2. The authority
+    ///   can be transferred, and new lookup tables have the registry account as
+    ///   their authority. Delegates can sign for the authority, an authority
+    ///   can have registries in multiple namespaces, the authority can be a
+    ///   multisig, entries have metadata and record when they are
+    ///   deactivated, and anyone can close deactivated lookup tables that have
+    ///   a close recipient. Adds `seed_authority`, `namespace`,
+    ///   `pending_authority`, `delegates`, `multisig`, [RegistryEntry::metadata],
+    ///   [RegistryEntry::deactivation_slot] and [RegistryEntry::close_recipient].
+    ///   The account is accessed without copying it, and the entries follow a
+    ///   fixed header instead of being serialized with their length.
+    ///
+    /// Accounts of version 0 are migrated to the current version with the
+    /// `migrate_registry` instruction, see [crate::migration].
     pub version: u8,
     /// The seed returned when deriving the registry account's address
     pub seed: [u8; 1],
@@ -31,46 +82,420 @@ pub struct RegistryAccount {
     pub len: u8,
     /// The allocated capacity of lookup entries. The capacity can be > len
     pub capacity: u8,
+    /// The number of overflow pages that have been added to the registry
+    pub pages: u8,
+    /// The length of the namespace
+    pub namespace_len: u8,
     /// Reserved bytes used as padding
-    pub reserved0: [u8; 4],
+    pub reserved0: [u8; 2],
     /// The slot when the last lookup table was created.
     /// Used to prevent a user creating multiple addresses in same slot
     pub last_created_slot: u64,
-    /// A growable list of registry entries
-    pub tables: Vec<RegistryEntry>,
+    /// The key that the registry account's address is derived from. This is the
+    /// original authority, and does not change when the authority is transferred.
+    pub seed_authority: Pubkey,
+    /// The namespace that the registry account's address is derived from,
+    /// padded with zeros. Registries without a namespace have an empty namespace.
+    pub namespace: [u8; MAX_NAMESPACE_LEN],
+    /// The authority that has been proposed to take over the registry, or the
+    /// default pubkey if no transfer is in progress
+    pub pending_authority: Pubkey,
+    /// The keys that can sign for the authority with a subset of its permissions.
+    /// Unused delegates have a default key and no permissions.
+    pub delegates: [Delegate; MAX_DELEGATES],
+    /// The signers that act as the authority in multisig mode, in which case
+    /// the `authority` can't sign alone
+    pub multisig: Multisig,
+    /// Reserved bytes in place of the length of the entries in version 0,
+    /// which also align the entries
+    pub reserved1: [u8; 8],
+}
+
+impl RegistryAccount {
+    /// The number of the next overflow page, which errors if the registry has
+    /// [MAX_REGISTRY_PAGES] already
+    pub fn next_page(&self) -> Result<u8> {
+        if self.pages as usize >= MAX_REGISTRY_PAGES {
+            return err!(crate::ErrorCode::TooManyEntries);
+        }
+        Ok(self.pages + 1)
+    }
+
+    /// The seeds that the program signs for the registry account with
+    pub fn signer_seeds(&self) -> [&[u8]; 3] {
+        [self.seed_authority.as_ref(), self.namespace(), &self.seed]
+    }
+
+    /// The namespace of the registry, without padding
+    pub fn namespace(&self) -> &[u8] {
+        &self.namespace[..self.namespace_len as usize]
+    }
+
+    /// Whether the signer can sign as the authority. In multisig mode, this is
+    /// any signer of the multisig, and the instruction checks that enough
+    /// signers have signed.
+    pub fn is_authority(&self, signer: &Pubkey) -> bool {
+        if self.multisig.is_enabled() {
+            self.multisig.is_signer(signer)
+        } else {
+            signer == &self.authority
+        }
+    }
+
+    /// Whether the signer is a delegate that has all the permissions requested.
+    /// See [crate::permissions].
+    pub fn is_delegate(&self, signer: &Pubkey, permissions: u8) -> bool {
+        self.delegates.iter().any(|delegate| {
+            &delegate.key == signer && delegate.permissions & permissions == permissions
+        })
+    }
+
+    /// Whether the signer can sign as the authority, or is a delegate that has
+    /// all the permissions requested.
+    pub fn can_sign(&self, signer: &Pubkey, permissions: u8) -> bool {
+        self.is_authority(signer) || self.is_delegate(signer, permissions)
+    }
+}
+
+/// A key that can sign for the authority of a registry
+#[zero_copy]
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Default, PartialEq, Eq)]
+pub struct Delegate {
+    /// The key of the delegate
+    pub key: Pubkey,
+    /// The permissions of the delegate, see [crate::permissions]
+    pub permissions: u8,
+    /// Reserved bytes used as padding
+    pub reserved0: [u8; 7],
+}
+
+/// Signers that act together as the authority of a registry
+#[zero_copy]
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Default, PartialEq, Eq)]
+pub struct Multisig {
+    /// The signers of the multisig. Unused signers have a default key.
+    pub signers: [Pubkey; MAX_MULTISIG_SIGNERS],
+    /// The number of signers that must sign, or 0 if the multisig is not used
+    pub threshold: u8,
+    /// Reserved bytes used as padding
+    pub reserved0: [u8; 7],
+}
+
+impl Multisig {
+    /// Whether the registry is in multisig mode
+    pub fn is_enabled(&self) -> bool {
+        self.threshold > 0
+    }
+
+    /// Whether the key is one of the signers
+    pub fn is_signer(&self, key: &Pubkey) -> bool {
+        key != &Pubkey::default() && self.signers.contains(key)
+    }
+
+    /// The number of signers of the multisig that are among the keys
+    pub fn approvals<'a>(&self, keys: impl IntoIterator<Item = &'a Pubkey>) -> usize {
+        let keys = keys.into_iter().collect::<Vec<_>>();
+        self.signers
+            .iter()
+            .filter(|signer| self.is_signer(signer) && keys.contains(signer))
+            .count()
+    }
+}
+
+/// An overflow page of a registry account, used to store entries once the
+/// registry account is full.
+///
+/// Pages are numbered from 1, and their address is derived from the registry
+/// account's address and the page number. Like the registry account, the
+/// entries of a page follow its header in the account data.
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct RegistryPage {
+    /// The registry account that the page belongs to
+    pub registry: Pubkey,
+    /// The number of the page, starting from 1
+    pub page: u8,
+    /// The seed returned when deriving the page's address
+    pub seed: [u8; 1],
+    /// The length of the populated entries in the page
+    pub len: u8,
+    /// The allocated capacity of lookup entries. The capacity can be > len
+    pub capacity: u8,
+    /// Reserved bytes used as padding
+    pub reserved0: [u8; 4],
+    /// Reserved bytes used as padding, which align the entries
+    pub reserved1: [u8; 8],
 }
 
 /// An entry that tracks a lookup table and its state.
-#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
+#[zero_copy]
+#[derive(AnchorSerialize, AnchorDeserialize, Debug)]
 pub struct RegistryEntry {
     /// An identifier to track the state (and in future purpose) of an entry
     pub discriminator: u64,
     /// The lookup table address
     pub table: Pubkey,
+    /// Information about the lookup table for its users
+    pub metadata: EntryMetadata,
+    /// The slot when the lookup table was deactivated, or 0 if it is active or
+    /// was deactivated before the registry was migrated from version 0
+    pub deactivation_slot: u64,
+    /// The recipient of the lookup table's lamports when anyone closes it with
+    /// `close_deactivated_table`, or the default pubkey if only the authority
+    /// can close it
+    pub close_recipient: Pubkey,
 }
 
-impl RegistryAccount {
+/// A lookup table to create with the `create_lookup_tables` instruction
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy)]
+pub struct NewLookupTable {
+    /// The recent slot that the lookup table address is derived from
+    pub recent_slot: u64,
+    /// The discriminator of the lookup table
+    pub discriminator: u64,
+}
+
+/// Information about what a lookup table is used for
+#[zero_copy]
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Default, PartialEq, Eq)]
+pub struct EntryMetadata {
+    /// A human-readable label, padded with zeros
+    pub label: [u8; MAX_LABEL_LEN],
+    /// The program that the lookup table is used with, or the default pubkey
+    pub program: Pubkey,
+    /// The slot when the lookup table was added to the registry
+    pub created_slot: u64,
+}
+
+impl EntryMetadata {
+    /// The label without its padding
+    pub fn label(&self) -> &[u8] {
+        let len = self
+            .label
+            .iter()
+            .rposition(|b| *b != 0)
+            .map_or(0, |last| last + 1);
+        &self.label[..len]
+    }
+}
+
+impl RegistryEntry {
+    /// Whether the lookup table has been frozen through the registry
+    pub fn is_frozen(&self) -> bool {
+        self.discriminator & crate::discriminator::FROZEN != 0
+    }
+
+    /// Whether anyone can close the lookup table once it has been deactivated
+    /// and has cooled down, see [RegistryEntry::close_recipient]
+    pub fn is_auto_close(&self) -> bool {
+        self.close_recipient != Pubkey::default()
+    }
+
+    /// The earliest slot when a deactivated lookup table can be closed, or none
+    /// if the lookup table is not deactivated or its deactivation slot is unknown.
+    ///
+    /// This assumes that no slots are skipped after the deactivation slot, so it
+    /// is not a guarantee. A lookup table can only be closed once its
+    /// deactivation slot is no longer in the slot hashes, which takes longer
+    /// when slots are skipped, and closing it can still fail after this slot.
+    pub fn closeable_at_slot(&self) -> Option<u64> {
+        (self.discriminator == crate::discriminator::DEACTIVATED && self.deactivation_slot != 0)
+            .then(|| self.deactivation_slot + solana_program::slot_hashes::MAX_ENTRIES as u64 + 1)
+    }
+}
+
+/// The lookup table entries stored in a [RegistryAccount] or a [RegistryPage]
+pub trait RegistryTables {
+    /// The entries, including empty ones
+    fn tables(&self) -> &[RegistryEntry];
+
+    /// The entries for mutation
+    fn tables_mut(&mut self) -> &mut [RegistryEntry];
+
     /// Find an entry in the registry by its address
-    pub fn find_entry(&self, address: &Pubkey) -> Result<&RegistryEntry> {
-        self.tables
+    fn find_entry(&self, address: &Pubkey) -> Result<&RegistryEntry> {
+        self.tables()
             .iter()
             .find(|entry| &entry.table == address)
             .ok_or(crate::ErrorCode::InvalidLookupTable.into())
     }
 
     /// Find an entry in the registry by its address for mutation
-    pub fn find_entry_mut(&mut self, address: &Pubkey) -> Result<&mut RegistryEntry> {
-        self.tables
+    fn find_entry_mut(&mut self, address: &Pubkey) -> Result<&mut RegistryEntry> {
+        self.tables_mut()
             .iter_mut()
             .find(|entry| &entry.table == address)
             .ok_or(crate::ErrorCode::InvalidLookupTable.into())
     }
 
     /// Find an empty entry in the registry. An empty entry is one with a discriminator = [crate::discriminator::EMPTY]
-    pub fn find_empty_entry(&mut self) -> Result<&mut RegistryEntry> {
-        self.tables
+    fn find_empty_entry(&mut self) -> Result<&mut RegistryEntry> {
+        self.tables_mut()
             .iter_mut()
             .find(|entry| entry.discriminator == crate::discriminator::EMPTY)
             .ok_or(crate::ErrorCode::InvalidLookupTable.into())
     }
 }
+
+impl RegistryTables for [RegistryEntry] {
+    fn tables(&self) -> &[RegistryEntry] {
+        self
+    }
+
+    fn tables_mut(&mut self) -> &mut [RegistryEntry] {
+        self
+    }
+}
+
+/// The header of a [RegistryAccount] or a [RegistryPage], which is followed by
+/// its entries in the account data
+pub trait RegistryHeader {
+    /// The version of the layout, see [RegistryAccount::version]
+    fn version(&self) -> u8;
+
+    /// The allocated capacity of entries, which is the number of entries that
+    /// follow the header
+    fn capacity(&self) -> u8;
+
+    /// The number of populated entries and the allocated capacity
+    fn len_and_capacity_mut(&mut self) -> (&mut u8, &mut u8);
+}
+
+impl RegistryHeader for RegistryAccount {
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn capacity(&self) -> u8 {
+        self.capacity
+    }
+
+    fn len_and_capacity_mut(&mut self) -> (&mut u8, &mut u8) {
+        (&mut self.len, &mut self.capacity)
+    }
+}
+
+impl RegistryHeader for RegistryPage {
+    /// Pages have only been created with the current layout
+    fn version(&self) -> u8 {
+        REGISTRY_VERSION
+    }
+
+    fn capacity(&self) -> u8 {
+        self.capacity
+    }
+
+    fn len_and_capacity_mut(&mut self) -> (&mut u8, &mut u8) {
+        (&mut self.len, &mut self.capacity)
+    }
+}
+
+/// Borrow a [RegistryAccount] or a [RegistryPage] and its entries from the
+/// account data, without copying them.
+///
+/// Registry accounts of version 0 are rejected with [crate::ErrorCode::InvalidVersion],
+/// as their layout differs. They can be read with `try_deserialize_any_version`
+/// and migrated with the `migrate_registry` instruction.
+pub trait LoadRegistry<T: RegistryHeader> {
+    /// Borrow the header
+    fn load_header(&self) -> Result<Ref<'_, T>>;
+
+    /// Borrow the header for mutation
+    fn load_header_mut(&self) -> Result<RefMut<'_, T>>;
+
+    /// Borrow the entries
+    fn load_tables(&self) -> Result<Ref<'_, [RegistryEntry]>>;
+
+    /// Borrow the header and the entries for mutation
+    fn load_tables_mut(&self) -> Result<(RefMut<'_, T>, RefMut<'_, [RegistryEntry]>)>;
+}
+
+impl<'info, T: RegistryHeader + ZeroCopy + Owner> LoadRegistry<T> for AccountLoader<'info, T> {
+    fn load_header(&self) -> Result<Ref<'_, T>> {
+        check_layout::<T>(&self.as_ref().try_borrow_data()?)?;
+        self.load()
+    }
+
+    fn load_header_mut(&self) -> Result<RefMut<'_, T>> {
+        check_layout::<T>(&self.as_ref().try_borrow_data()?)?;
+        self.load_mut()
+    }
+
+    fn load_tables(&self) -> Result<Ref<'_, [RegistryEntry]>> {
+        let data = self.as_ref().try_borrow_data()?;
+        let end = tables_end(check_layout::<T>(&data)?);
+        Ok(Ref::map(data, |data| {
+            bytemuck::cast_slice(&data[8 + std::mem::size_of::<T>()..end])
+        }))
+    }
+
+    fn load_tables_mut(&self) -> Result<(RefMut<'_, T>, RefMut<'_, [RegistryEntry]>)> {
+        if !self.as_ref().is_writable {
+            return err!(ErrorCode::AccountNotMutable);
+        }
+        let data = self.as_ref().try_borrow_mut_data()?;
+        let end = tables_end(check_layout::<T>(&data)?);
+        Ok(RefMut::map_split(data, |data| {
+            let (header, tables) = data[8..end].split_at_mut(std::mem::size_of::<T>());
+            (
+                bytemuck::from_bytes_mut(header),
+                bytemuck::cast_slice_mut(tables),
+            )
+        }))
+    }
+}
+
+/// Check that account data has the current layout of a registry account or
+/// page, and that it is long enough for its entries. Returns the header.
+fn check_layout<T: RegistryHeader + ZeroCopy + Owner>(data: &[u8]) -> Result<&T> {
+    if data.get(..8) != Some(&T::discriminator()) {
+        return err!(ErrorCode::AccountDiscriminatorMismatch);
+    }
+    // Only registry accounts of version 0 can be shorter than the header
+    let header = data
+        .get(8..8 + std::mem::size_of::<T>())
+        .ok_or(crate::ErrorCode::InvalidVersion)?;
+    let header: &T =
+        bytemuck::try_from_bytes(header).map_err(|_| ErrorCode::AccountDidNotDeserialize)?;
+    if header.version() != REGISTRY_VERSION {
+        return err!(crate::ErrorCode::InvalidVersion);
+    }
+    if data.len() < tables_end(header) {
+        return err!(ErrorCode::AccountDidNotDeserialize);
+    }
+    Ok(header)
+}
+
+/// The end of the entries that follow a header in the account data
+fn tables_end<T: RegistryHeader>(header: &T) -> usize {
+    8 + std::mem::size_of::<T>() + header.capacity() as usize * REGISTRY_ENTRY_SIZE
+}
+
+/// A [RegistryAccount] or a [RegistryPage] decoded from its account data,
+/// together with its entries
+#[derive(Debug, Clone)]
+pub struct Decoded<T> {
+    /// The header of the account
+    pub header: T,
+    /// The entries that follow the header, including empty ones
+    pub tables: Vec<RegistryEntry>,
+}
+
+impl<T> std::ops::Deref for Decoded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.header
+    }
+}
+
+impl<T> RegistryTables for Decoded<T> {
+    fn tables(&self) -> &[RegistryEntry] {
+        &self.tables
+    }
+
+    fn tables_mut(&mut self) -> &mut [RegistryEntry] {
+        &mut self.tables
+    }
+}
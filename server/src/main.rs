@@ -30,6 +30,10 @@ async fn main() {
             "/lookup/authority_addresses/:authority",
             get(get_authority_addresses),
         )
+        .route(
+            "/lookup/authority_addresses/:authority/:namespace",
+            get(get_namespaced_authority_addresses),
+        )
         .layer(CorsLayer::permissive())
         .layer(Extension(context));
 
@@ -45,13 +49,32 @@ async fn get_authority_addresses(
     Extension(context): Extension<ApiContext>,
     Path(authority): Path<String>,
 ) -> impl IntoResponse {
+    authority_addresses(context, authority, String::new()).await
+}
+
+async fn get_namespaced_authority_addresses(
+    Extension(context): Extension<ApiContext>,
+    Path((authority, namespace)): Path<(String, String)>,
+) -> impl IntoResponse {
+    authority_addresses(context, authority, namespace).await
+}
+
+async fn authority_addresses(
+    context: ApiContext,
+    authority: String,
+    namespace: String,
+) -> Json<GetAuthorityAddressesResponse> {
     // Check that authority is a valid pubkey
     let Ok(authority) = authority.parse::<Pubkey>() else {
-        return Json(GetAuthorityAddressesResponse { authority: Default::default(), addresses: vec![] })
+        return Json(GetAuthorityAddressesResponse {
+            authority: Default::default(),
+            namespace,
+            addresses: vec![],
+        });
     };
     let addresses = context
         .registry_client
-        .get_registry(&authority)
+        .get_registry(&authority, &namespace)
         .await
         .map(|registry| {
             registry
@@ -63,6 +86,7 @@ async fn get_authority_addresses(
         .unwrap_or_default();
     Json(GetAuthorityAddressesResponse {
         authority,
+        namespace,
         addresses,
     })
 }
@@ -74,16 +98,17 @@ async fn get_lookup_addresses(
     // Refresh lookup addresses by authority
     context
         .registry_client
-        .update_registries(&input.authorities)
+        .update_registries(&input.authorities, &input.namespace)
         .await;
     let instructions = input
         .instructions
         .iter()
         .map(|ix| ix.into())
         .collect::<Vec<_>>();
-    let result = context
-        .registry_client
-        .find_addresses(&instructions, &input.authorities);
+    let result =
+        context
+            .registry_client
+            .find_addresses(&instructions, &input.authorities, &input.namespace);
 
     Json(GetAddressesResponse {
         distinct_accounts: result.distinct,
@@ -106,6 +131,7 @@ struct GetAddressesResponse {
 struct GetAuthorityAddressesResponse {
     #[serde_as(as = "DisplayFromStr")]
     authority: Pubkey,
+    namespace: String,
     #[serde_as(as = "Vec<DisplayFromStr>")]
     addresses: Vec<Pubkey>,
 }
@@ -116,6 +142,9 @@ struct GetLookupAddressInput {
     instructions: Vec<InstructionSmall>,
     #[serde_as(as = "Vec<DisplayFromStr>")]
     authorities: Vec<Pubkey>,
+    /// The namespace of the registries, empty for registries not in a namespace
+    #[serde(default)]
+    namespace: String,
 }
 
 #[derive(Clone)]
@@ -29,12 +29,12 @@ describe("lookup-table-registry", () => {
     const [registryAccount, _] = publicKey.findProgramAddressSync([provider.publicKey.toBytes()], program.programId)
     recentSlot = await provider.connection.getSlot();
     const [_ix, lookupTable] = AddressLookupTableProgram.createLookupTable({
-      authority: provider.publicKey,
+      authority: registryAccount,
       payer: provider.publicKey,
       recentSlot
     });
     lookupTableAddress = lookupTable;
-    const tx = await program.methods.createLookupTable(new anchor.BN(recentSlot), new anchor.BN(1)).accounts({
+    const tx = await program.methods.createLookupTable(new anchor.BN(recentSlot), new anchor.BN(2)).accounts({
       authority: provider.publicKey,
       payer: provider.publicKey,
       registryAccount,
@@ -52,7 +52,7 @@ describe("lookup-table-registry", () => {
       TOKEN_PROGRAM_ID,
       provider.publicKey,
     ];
-    const tx = await program.methods.appendToLookupTable(new anchor.BN(1), newAddresses).accounts({
+    const tx = await program.methods.appendToLookupTable(newAddresses, new anchor.BN(2), true).accounts({
       authority: provider.publicKey,
       payer: provider.publicKey,
       registryAccount,
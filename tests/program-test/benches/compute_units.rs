@@ -0,0 +1,270 @@
+//! Measure the compute units that the instructions of the registry program
+//! consume, and compare them with the report in `compute-units.txt`.
+//!
+//! Instructions are measured on registries of several sizes, where the size
+//! is the number of entries when the instruction runs. A registry account
+//! or page holds at most [MAX_REGISTRY_ENTRIES], so larger registries have
+//! pages, and the instruction runs on the page of the last entry.
+//!
+//! Run it with `./check compute-units`. It fails if an instruction consumes
+//! more compute units than in the report, or if there is no report. Pass
+//! `--save` to write the report after an intended change, and commit it with
+//! the change.
+
+use std::{collections::HashMap, fmt::Write, fs, path::Path};
+
+use lookup_table_registry::{discriminator, EntryMetadata, MAX_REGISTRY_ENTRIES};
+use lookup_table_registry_program_test::RegistryTest;
+use solana_sdk::{
+    hash::hashv, pubkey::Pubkey, signer::keypair::keypair_from_seed, slot_hashes::MAX_ENTRIES,
+};
+
+/// The report that measurements are compared with
+const REPORT: &str = "compute-units.txt";
+
+/// The number of entries of the registries that instructions are measured on
+const REGISTRY_SIZES: [usize; 3] = [1, 64, 254];
+
+/// The number of addresses appended to a lookup table. 30 addresses don't fit
+/// in a transaction on a cluster, but banks don't limit the transaction size.
+const APPEND_COUNTS: [usize; 3] = [1, 10, 30];
+
+/// The authority is the same between runs, so that the compute units used to
+/// derive its addresses are too
+const AUTHORITY_SEED: [u8; 32] = [7; 32];
+
+#[derive(Debug)]
+struct Measurement {
+    instruction: String,
+    entries: usize,
+    units: u64,
+}
+
+#[tokio::main]
+async fn main() {
+    let save = std::env::args().any(|arg| arg == "--save");
+
+    let mut measurements = vec![measure_init().await];
+    for entries in REGISTRY_SIZES {
+        measurements.push(measure_create(entries).await);
+        measurements.push(measure_create_in_empty_entry(entries).await);
+        for count in APPEND_COUNTS {
+            measurements.push(measure_append(entries, count).await);
+        }
+        measurements.extend(measure_remove(entries).await);
+    }
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(REPORT);
+    if save {
+        fs::write(&path, format_report(&measurements)).unwrap();
+        println!("{}", format_report(&measurements));
+        println!("Saved the report to {}", path.display());
+        return;
+    }
+    let baseline = match fs::read_to_string(&path) {
+        Ok(report) => parse_report(&report),
+        Err(e) => {
+            println!("{}", format_report(&measurements));
+            eprintln!(
+                "Can't read the report {}: {e}, save it with `--save`",
+                path.display()
+            );
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "{:<32} {:>7} {:>9} {:>9} {:>7}",
+        "instruction", "entries", "units", "baseline", "change"
+    );
+    let mut regressions = 0;
+    for measurement in &measurements {
+        let key = (measurement.instruction.clone(), measurement.entries);
+        let Some(&before) = baseline.get(&key) else {
+            println!(
+                "{:<32} {:>7} {:>9} {:>9}",
+                measurement.instruction, measurement.entries, measurement.units, "-"
+            );
+            continue;
+        };
+        let change = measurement.units as i64 - before as i64;
+        if change > 0 {
+            regressions += 1;
+        }
+        println!(
+            "{:<32} {:>7} {:>9} {:>9} {:>+7}",
+            measurement.instruction, measurement.entries, measurement.units, before, change
+        );
+    }
+
+    if regressions > 0 {
+        eprintln!(
+            "{regressions} instructions consume more compute units than in {}, \
+            save the report with `--save` if this is intended",
+            path.display()
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Start a bank with the same authority for every measurement
+async fn start() -> RegistryTest {
+    RegistryTest::start_with_authority(keypair_from_seed(&AUTHORITY_SEED).unwrap()).await
+}
+
+/// Start a bank with a registry of the authority that has a number of entries,
+/// and return the page that the next entry is stored in
+async fn start_with_registry(entries: usize) -> (RegistryTest, u8) {
+    let mut test = start().await;
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+    let page = test.fill_registry(entries).await;
+
+    (test, page)
+}
+
+async fn measure_init() -> Measurement {
+    let mut test = start().await;
+    let units = test
+        .process_with_compute_units(&[test.builder.init_registry()])
+        .await
+        .unwrap();
+
+    Measurement {
+        instruction: "init_registry_account".to_string(),
+        entries: 0,
+        units,
+    }
+}
+
+/// Create a lookup table that is appended to the end of the registry
+async fn measure_create(entries: usize) -> Measurement {
+    let (mut test, page) = start_with_registry(entries).await;
+    let recent_slot = test.next_recent_slot().await;
+    let (create, _) = test.builder.create_lookup_table(recent_slot, 2, page);
+    let units = test.process_with_compute_units(&[create]).await.unwrap();
+
+    Measurement {
+        instruction: "create_lookup_table/append".to_string(),
+        entries,
+        units,
+    }
+}
+
+/// Create a lookup table in the entry of a closed lookup table, which is the
+/// last entry of the registry
+async fn measure_create_in_empty_entry(entries: usize) -> Measurement {
+    let (mut test, _) = start_with_registry(entries).await;
+    let page = ((entries - 1) / MAX_REGISTRY_ENTRIES) as u8;
+    let mut tables = if page == 0 {
+        test.registry().await.tables
+    } else {
+        test.registry_page(page).await.tables
+    };
+    let last = tables.last_mut().unwrap();
+    last.discriminator = discriminator::EMPTY;
+    last.table = Pubkey::default();
+    last.metadata = EntryMetadata::default();
+    test.set_tables(page, &tables).await;
+
+    let recent_slot = test.next_recent_slot().await;
+    let (create, _) = test.builder.create_lookup_table(recent_slot, 2, page);
+    let units = test.process_with_compute_units(&[create]).await.unwrap();
+
+    Measurement {
+        instruction: "create_lookup_table/reuse".to_string(),
+        entries,
+        units,
+    }
+}
+
+/// Append addresses to the lookup table of the last entry of the registry
+async fn measure_append(entries: usize, count: usize) -> Measurement {
+    let (mut test, page, lookup_table) = start_with_lookup_table(entries).await;
+    let addresses = (0..count)
+        .map(|index| {
+            Pubkey::new_from_array(hashv(&[b"address".as_ref(), &index.to_le_bytes()]).to_bytes())
+        })
+        .collect::<Vec<_>>();
+    let append = test
+        .builder
+        .append_to_lookup_table(lookup_table, &addresses, 2, true, page);
+    let units = test.process_with_compute_units(&[append]).await.unwrap();
+
+    Measurement {
+        instruction: format!("append_to_lookup_table/{count}"),
+        entries,
+        units,
+    }
+}
+
+/// Deactivate the lookup table of the last entry of the registry, and close
+/// it once it has cooled down
+async fn measure_remove(entries: usize) -> [Measurement; 2] {
+    let (mut test, page, lookup_table) = start_with_lookup_table(entries).await;
+    let remove = test.builder.remove_lookup_table(lookup_table, page);
+    let deactivate = test
+        .process_with_compute_units(&[remove.clone()])
+        .await
+        .unwrap();
+
+    let slot = test.slot().await;
+    test.warp_to_slot(slot + MAX_ENTRIES as u64 + 1).await;
+    let close = test.process_with_compute_units(&[remove]).await.unwrap();
+
+    [
+        Measurement {
+            instruction: "remove_lookup_table/deactivate".to_string(),
+            entries,
+            units: deactivate,
+        },
+        Measurement {
+            instruction: "remove_lookup_table/close".to_string(),
+            entries,
+            units: close,
+        },
+    ]
+}
+
+/// Start a bank with a registry that has a number of entries, the last of
+/// which is a lookup table that exists. Returns the page of the lookup table.
+async fn start_with_lookup_table(entries: usize) -> (RegistryTest, u8, Pubkey) {
+    let (mut test, page) = start_with_registry(entries - 1).await;
+    let recent_slot = test.next_recent_slot().await;
+    let (create, lookup_table) = test.builder.create_lookup_table(recent_slot, 2, page);
+    test.process(&[create]).await.unwrap();
+
+    (test, page, lookup_table)
+}
+
+fn format_report(measurements: &[Measurement]) -> String {
+    let mut report = format!(
+        "# Compute units consumed by the registry program, see benches/compute_units.rs\n\
+        {:<32} {:>7} {:>9}\n",
+        "instruction", "entries", "units"
+    );
+    for measurement in measurements {
+        writeln!(
+            report,
+            "{:<32} {:>7} {:>9}",
+            measurement.instruction, measurement.entries, measurement.units
+        )
+        .unwrap();
+    }
+
+    report
+}
+
+/// The compute units of a report, keyed by the instruction and the number of entries
+fn parse_report(report: &str) -> HashMap<(String, usize), u64> {
+    report
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace();
+            let instruction = columns.next()?.to_string();
+            let entries = columns.next()?.parse().ok()?;
+            let units = columns.next()?.parse().ok()?;
+            Some(((instruction, entries), units))
+        })
+        .collect()
+}
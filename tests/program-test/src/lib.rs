@@ -0,0 +1,351 @@
+//! A local environment to test the registry program without a validator.
+//!
+//! The compiled program runs in a `solana-program-test` bank, together with
+//! the address lookup table program that is built into the runtime. Tests can
+//! warp to later slots, so that lookup tables cool down without waiting.
+//!
+//! The program is loaded from `lookup_table_registry.so` in `SBF_OUT_DIR`, so
+//! build it with `anchor build` first. `./check program-test` does both.
+
+use std::borrow::Cow;
+
+use anchor_lang::Discriminator;
+use bytemuck::{bytes_of, cast_slice};
+use lookup_table_registry::{
+    discriminator, Decoded, EntryMetadata, RegistryAccount, RegistryEntry, RegistryPage,
+    MAX_REGISTRY_ENTRIES, REGISTRY_ENTRY_SIZE,
+};
+use lookup_table_registry_client::{instructions::InstructionBuilder, LOOKUP_TABLE_REGISTRY_ID};
+use solana_address_lookup_table_program_gateway::{
+    instruction::create_lookup_table, state::AddressLookupTable,
+};
+use solana_program_test::{BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    clock::{Clock, Slot},
+    hash::{hashv, Hash},
+    instruction::{Instruction, InstructionError},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    slot_hashes::{SlotHashes, MAX_ENTRIES},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+/// The lamports that the authority starts with
+const AUTHORITY_LAMPORTS: u64 = 100 * LAMPORTS_PER_SOL;
+
+/// A bank with the registry program, and an authority to sign for its registry
+pub struct RegistryTest {
+    pub context: ProgramTestContext,
+    /// The authority of the registry, which also pays for transactions and rent
+    pub authority: Keypair,
+    /// Builds the instructions that the authority signs
+    pub builder: InstructionBuilder,
+}
+
+impl RegistryTest {
+    /// Start a bank with the program, and a funded authority that doesn't
+    /// have a registry yet
+    pub async fn start() -> Self {
+        Self::start_with_authority(Keypair::new()).await
+    }
+
+    /// Start a bank as [Self::start] with a given authority. The addresses
+    /// derived from the authority, and the compute units used to derive
+    /// them, stay the same between runs.
+    pub async fn start_with_authority(authority: Keypair) -> Self {
+        let mut program_test =
+            ProgramTest::new("lookup_table_registry", LOOKUP_TABLE_REGISTRY_ID, None);
+        program_test.prefer_bpf(true);
+        program_test.add_account(
+            authority.pubkey(),
+            Account::new(AUTHORITY_LAMPORTS, 0, &system_program::ID),
+        );
+        let context = program_test.start_with_context().await;
+        let builder = InstructionBuilder::new(authority.pubkey(), authority.pubkey());
+
+        Self {
+            context,
+            authority,
+            builder,
+        }
+    }
+
+    /// Process a transaction that the authority signs and pays for.
+    ///
+    /// Each transaction waits for a new blockhash, so that repeating an
+    /// instruction isn't rejected as a duplicate transaction.
+    pub async fn process(&mut self, instructions: &[Instruction]) -> Result<(), BanksClientError> {
+        self.process_signed(instructions, &[]).await
+    }
+
+    /// Process a transaction that other keys sign together with the authority,
+    /// such as a delegate, a new authority or the signers of a multisig
+    pub async fn process_signed(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let transaction = self.transaction(instructions, signers).await?;
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    /// Process a transaction as [Self::process], and return the compute units
+    /// that it consumed
+    pub async fn process_with_compute_units(
+        &mut self,
+        instructions: &[Instruction],
+    ) -> Result<u64, BanksClientError> {
+        let transaction = self.transaction(instructions, &[]).await?;
+        let processed = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await?;
+        processed.result?;
+
+        Ok(processed.metadata.unwrap().compute_units_consumed)
+    }
+
+    async fn transaction(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Transaction, BanksClientError> {
+        let blockhash = self.context.get_new_latest_blockhash().await?;
+        let mut keypairs = vec![&self.authority];
+        keypairs.extend_from_slice(signers);
+
+        Ok(Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.authority.pubkey()),
+            &keypairs,
+            blockhash,
+        ))
+    }
+
+    /// The current slot of the bank
+    pub async fn slot(&mut self) -> Slot {
+        let clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.slot
+    }
+
+    /// Warp to a later slot.
+    ///
+    /// The slot hashes are replaced by those of the slots before it, as on a
+    /// cluster that hasn't skipped any slots. Lookup tables can then be
+    /// derived from the recent slots, and lookup tables that were deactivated
+    /// more than [MAX_ENTRIES] slots ago can be closed.
+    pub async fn warp_to_slot(&mut self, slot: Slot) {
+        self.context.warp_to_slot(slot).unwrap();
+        let slot_hashes = (slot.saturating_sub(MAX_ENTRIES as Slot)..slot)
+            .rev()
+            .map(|slot| (slot, Hash::new_unique()))
+            .collect::<Vec<_>>();
+        self.context.set_sysvar(&SlotHashes::new(&slot_hashes));
+    }
+
+    /// Warp two slots ahead, and return the slot in between to derive a lookup
+    /// table from. The registry needs a recent slot for each lookup table that
+    /// is later than the slots used before, including the slot that the
+    /// registry was created in.
+    pub async fn next_recent_slot(&mut self) -> Slot {
+        let slot = self.slot().await;
+        self.warp_to_slot(slot + 2).await;
+        slot + 1
+    }
+
+    /// Create a lookup table outside of the registry, which a key is the
+    /// authority of, and return its address
+    pub async fn create_lookup_table_of(&mut self, authority: Pubkey) -> Pubkey {
+        let recent_slot = self.next_recent_slot().await;
+        let (create, lookup_table) =
+            create_lookup_table(authority, self.authority.pubkey(), recent_slot);
+        self.process(&[create]).await.unwrap();
+        lookup_table
+    }
+
+    /// A builder of instructions that another key signs for the registry of
+    /// the authority, such as a delegate or a new authority
+    pub fn builder_for(&self, signer: Pubkey) -> InstructionBuilder {
+        InstructionBuilder::new(signer, self.authority.pubkey())
+            .with_seed_authority(self.authority.pubkey())
+    }
+
+    /// Fetch the registry account of the authority, and check that it is
+    /// consistent with its entries and its size
+    pub async fn registry(&mut self) -> Decoded<RegistryAccount> {
+        self.registry_at(self.builder.registry_address()).await
+    }
+
+    /// Fetch a registry account, such as a registry in a namespace, and check
+    /// that it is consistent with its entries and its size
+    pub async fn registry_at(&mut self, address: Pubkey) -> Decoded<RegistryAccount> {
+        let account = self.account(address).await.expect("registry account");
+        let registry = RegistryAccount::try_deserialize_any_version(&account.data).unwrap();
+        self.check_invariants(&registry.tables, registry.len, registry.capacity, &account)
+            .await;
+
+        registry
+    }
+
+    /// Fetch a page of the registry account, and check that it is consistent
+    /// with its entries and its size
+    pub async fn registry_page(&mut self, page: u8) -> Decoded<RegistryPage> {
+        let address = self.builder.registry_page_address(page);
+        let account = self.account(address).await.expect("registry page");
+        let registry_page = RegistryPage::try_deserialize_with_tables(&account.data).unwrap();
+        self.check_invariants(
+            &registry_page.tables,
+            registry_page.len,
+            registry_page.capacity,
+            &account,
+        )
+        .await;
+
+        registry_page
+    }
+
+    /// Fetch a lookup table, none if the account doesn't exist
+    pub async fn lookup_table(&mut self, address: Pubkey) -> Option<AddressLookupTable<'static>> {
+        let account = self.account(address).await?;
+        let table = AddressLookupTable::deserialize(&account.data).unwrap();
+
+        Some(AddressLookupTable {
+            meta: table.meta,
+            addresses: Cow::Owned(table.addresses.into_owned()),
+        })
+    }
+
+    /// Fetch an account, none if it doesn't exist
+    pub async fn account(&mut self, address: Pubkey) -> Option<Account> {
+        self.context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+    }
+
+    /// The lamports of the authority
+    pub async fn authority_lamports(&mut self) -> u64 {
+        let authority = self.authority.pubkey();
+        self.account(authority).await.unwrap().lamports
+    }
+
+    /// Fill the registry with active entries of lookup tables that don't
+    /// exist, to run instructions on a registry of a given size without
+    /// creating every lookup table.
+    ///
+    /// The entries are stored in the registry account until it is full, and
+    /// then in pages, as the client stores them. Returns the page that the
+    /// next entry is stored in, which is added if needed.
+    pub async fn fill_registry(&mut self, count: usize) -> u8 {
+        let last_page = (count / MAX_REGISTRY_ENTRIES) as u8;
+        for page in 1..=last_page {
+            self.process(&[self.builder.add_registry_page(page)])
+                .await
+                .unwrap();
+        }
+        for page in 0..=last_page {
+            let start = page as usize * MAX_REGISTRY_ENTRIES;
+            let end = count.min(start + MAX_REGISTRY_ENTRIES);
+            let tables = (start..end)
+                .map(|index| RegistryEntry {
+                    discriminator: 2,
+                    table: Pubkey::new_from_array(
+                        hashv(&[b"table".as_ref(), &index.to_le_bytes()]).to_bytes(),
+                    ),
+                    metadata: EntryMetadata::default(),
+                    deactivation_slot: 0,
+                    close_recipient: Pubkey::default(),
+                })
+                .collect::<Vec<_>>();
+            self.set_tables(page, &tables).await;
+        }
+
+        last_page
+    }
+
+    /// Replace the entries of the registry account, or of a page if it is not
+    /// 0, and resize the account for them. The `len` and `capacity` are set
+    /// to match the entries.
+    pub async fn set_tables(&mut self, page: u8, tables: &[RegistryEntry]) {
+        let len = tables
+            .iter()
+            .filter(|entry| entry.discriminator != discriminator::EMPTY)
+            .count() as u8;
+        let capacity = tables.len() as u8;
+        let (address, mut data) = if page == 0 {
+            let mut registry = self.registry().await.header;
+            registry.len = len;
+            registry.capacity = capacity;
+            let data = [&RegistryAccount::DISCRIMINATOR[..], bytes_of(&registry)].concat();
+            (self.builder.registry_address(), data)
+        } else {
+            let mut registry_page = self.registry_page(page).await.header;
+            registry_page.len = len;
+            registry_page.capacity = capacity;
+            let data = [&RegistryPage::DISCRIMINATOR[..], bytes_of(&registry_page)].concat();
+            (self.builder.registry_page_address(page), data)
+        };
+        data.extend_from_slice(cast_slice(tables));
+
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let account = Account {
+            lamports: rent.minimum_balance(data.len()),
+            data,
+            owner: LOOKUP_TABLE_REGISTRY_ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.context.set_account(&address, &account.into());
+    }
+
+    /// The `len` of a registry counts the entries that are not empty, and the
+    /// `capacity` counts all entries, which the account is sized for
+    async fn check_invariants(
+        &mut self,
+        tables: &[RegistryEntry],
+        len: u8,
+        capacity: u8,
+        account: &Account,
+    ) {
+        let populated = tables
+            .iter()
+            .filter(|entry| entry.discriminator != discriminator::EMPTY)
+            .count();
+        assert_eq!(len as usize, populated, "len of {tables:?}");
+        assert_eq!(capacity as usize, tables.len(), "capacity of {tables:?}");
+        assert!(capacity as usize <= MAX_REGISTRY_ENTRIES);
+        assert_eq!(
+            account.data.len() - capacity as usize * REGISTRY_ENTRY_SIZE,
+            header_size(&account.data),
+            "size of a registry with {capacity} entries"
+        );
+
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        assert!(rent.is_exempt(account.lamports, account.data.len()));
+    }
+}
+
+/// The error of a transaction whose first instruction failed with an error of
+/// the registry program or of Anchor
+pub fn instruction_error(error: impl Into<u32>) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error.into()))
+}
+
+/// The size of the discriminator and the header of a registry account or page
+fn header_size(data: &[u8]) -> usize {
+    let header = if data[..8] == RegistryAccount::DISCRIMINATOR {
+        std::mem::size_of::<RegistryAccount>()
+    } else {
+        std::mem::size_of::<RegistryPage>()
+    };
+    8 + header
+}
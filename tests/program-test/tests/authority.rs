@@ -0,0 +1,207 @@
+use lookup_table_registry::{discriminator, permissions, ErrorCode, NewLookupTable};
+use lookup_table_registry_program_test::{instruction_error, RegistryTest};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+/// The error of an account constraint that the signer doesn't meet
+fn constraint_error() -> solana_sdk::transaction::TransactionError {
+    instruction_error(anchor_lang::error::ErrorCode::ConstraintRaw)
+}
+
+#[tokio::test]
+async fn a_new_authority_changes_the_existing_lookup_tables() {
+    let mut test = RegistryTest::start().await;
+    let old_authority = test.authority.pubkey();
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+
+    // A lookup table created by the registry, and one that the authority
+    // created and imported
+    let recent_slot = test.next_recent_slot().await;
+    let (create, registry_table) = test.builder.create_lookup_table(recent_slot, 2, 0);
+    test.process(&[create]).await.unwrap();
+    let authority_table = test.create_lookup_table_of(old_authority).await;
+    let import = test.builder.import_lookup_table(authority_table, 3, 0);
+    test.process(&[import]).await.unwrap();
+
+    // Transfer the registry to a new authority
+    let new_authority = Keypair::new();
+    let builder = test.builder_for(new_authority.pubkey());
+    let propose = test.builder.propose_authority(new_authority.pubkey());
+    test.process(&[propose]).await.unwrap();
+    assert_eq!(
+        test.registry().await.pending_authority,
+        new_authority.pubkey()
+    );
+    test.process_signed(&[builder.accept_authority()], &[&new_authority])
+        .await
+        .unwrap();
+    let registry = test.registry().await;
+    assert_eq!(registry.authority, new_authority.pubkey());
+    assert_eq!(registry.seed_authority, old_authority);
+    assert_eq!(registry.pending_authority, Pubkey::default());
+
+    // The old authority can no longer sign for the registry
+    let addresses = [Pubkey::new_unique()];
+    let append = test
+        .builder
+        .append_to_lookup_table(registry_table, &addresses, 2, false, 0);
+    let error = test.process(&[append]).await.unwrap_err();
+    assert_eq!(error.unwrap(), constraint_error());
+
+    // The registry signs for its lookup table with the new authority
+    let append = builder.append_to_lookup_table(registry_table, &addresses, 2, false, 0);
+    test.process_signed(&[append], &[&new_authority])
+        .await
+        .unwrap();
+    let table = test.lookup_table(registry_table).await.unwrap();
+    assert_eq!(table.addresses.as_ref(), &addresses[..]);
+
+    // The old authority still has to sign for the lookup table that it is
+    // the authority of, together with the new authority
+    let builder = builder.with_table_authority(Some(old_authority));
+    let append = builder.append_to_lookup_table(authority_table, &addresses, 3, false, 0);
+    test.process_signed(&[append], &[&new_authority])
+        .await
+        .unwrap();
+    let table = test.lookup_table(authority_table).await.unwrap();
+    assert_eq!(table.addresses.as_ref(), &addresses[..]);
+
+    // Both lookup tables are removed by the new authority
+    let removes = [
+        builder.remove_lookup_table(registry_table, 0),
+        builder.remove_lookup_table(authority_table, 0),
+    ];
+    test.process_signed(&removes, &[&new_authority])
+        .await
+        .unwrap();
+    let registry = test.registry().await;
+    for entry in registry.tables.iter() {
+        assert_eq!(entry.discriminator, discriminator::DEACTIVATED);
+    }
+}
+
+#[tokio::test]
+async fn delegates_change_lookup_tables_of_the_registry() {
+    let mut test = RegistryTest::start().await;
+    let authority = test.authority.pubkey();
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+
+    let delegate = Keypair::new();
+    let builder = test.builder_for(delegate.pubkey());
+    let add_delegate = test
+        .builder
+        .add_delegate(delegate.pubkey(), permissions::APPEND | permissions::CREATE);
+    test.process(&[add_delegate]).await.unwrap();
+
+    // The delegate creates and extends lookup tables of the registry
+    let recent_slot = test.next_recent_slot().await;
+    let (create, lookup_table) = builder.create_lookup_table(recent_slot, 2, 0);
+    test.process_signed(&[create], &[&delegate]).await.unwrap();
+    let addresses = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let append = builder.append_to_lookup_table(lookup_table, &addresses, 2, false, 0);
+    test.process_signed(&[append], &[&delegate]).await.unwrap();
+    let table = test.lookup_table(lookup_table).await.unwrap();
+    assert_eq!(table.meta.authority, Some(test.builder.registry_address()));
+    assert_eq!(table.addresses.as_ref(), &addresses[..]);
+
+    // The delegate doesn't have the permission to remove lookup tables
+    let remove = builder.remove_lookup_table(lookup_table, 0);
+    let error = test
+        .process_signed(&[remove], &[&delegate])
+        .await
+        .unwrap_err();
+    assert_eq!(error.unwrap(), constraint_error());
+
+    // The delegate can't sign for lookup tables that the authority is the
+    // authority of
+    let authority_table = test.create_lookup_table_of(authority).await;
+    let import = test.builder.import_lookup_table(authority_table, 3, 0);
+    test.process(&[import]).await.unwrap();
+    let append = builder.append_to_lookup_table(authority_table, &addresses, 3, false, 0);
+    let error = test
+        .process_signed(&[append], &[&delegate])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        instruction_error(ErrorCode::RegistryNotTableAuthority)
+    );
+
+    // A revoked delegate can no longer sign
+    let revoke = test.builder.revoke_delegate(delegate.pubkey());
+    test.process(&[revoke]).await.unwrap();
+    let append = builder.append_to_lookup_table(lookup_table, &[Pubkey::new_unique()], 2, false, 0);
+    let error = test
+        .process_signed(&[append], &[&delegate])
+        .await
+        .unwrap_err();
+    assert_eq!(error.unwrap(), constraint_error());
+}
+
+#[tokio::test]
+async fn a_multisig_authority_needs_the_threshold_of_signers() {
+    let mut test = RegistryTest::start().await;
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+
+    let signers = [Keypair::new(), Keypair::new(), Keypair::new()];
+    let signer_keys = signers.iter().map(|s| s.pubkey()).collect::<Vec<_>>();
+    let set_multisig = test.builder.set_multisig(&signer_keys, 2);
+    test.process(&[set_multisig]).await.unwrap();
+    let multisig = test.registry().await.multisig;
+    assert_eq!(&multisig.signers[..3], &signer_keys[..]);
+    assert_eq!(multisig.threshold, 2);
+
+    // The authority is no longer a signer of the registry
+    let recent_slot = test.next_recent_slot().await;
+    let (create, _) = test.builder.create_lookup_table(recent_slot, 2, 0);
+    let error = test.process(&[create]).await.unwrap_err();
+    assert_eq!(error.unwrap(), constraint_error());
+
+    // One signer of the multisig isn't enough
+    let alone = test.builder_for(signer_keys[0]);
+    let (create, _) = alone.create_lookup_table(recent_slot, 2, 0);
+    let error = test
+        .process_signed(&[create], &[&signers[0]])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        instruction_error(ErrorCode::MultisigThresholdNotMet)
+    );
+
+    // Two signers create lookup tables, one at a time or together
+    let builder = alone.with_cosigners(&signer_keys[2..]);
+    let (create, lookup_table) = builder.create_lookup_table(recent_slot, 2, 0);
+    test.process_signed(&[create], &[&signers[0], &signers[2]])
+        .await
+        .unwrap();
+    let tables = [
+        NewLookupTable {
+            recent_slot: test.next_recent_slot().await,
+            discriminator: 3,
+        },
+        NewLookupTable {
+            recent_slot: test.next_recent_slot().await,
+            discriminator: 4,
+        },
+    ];
+    let (create, lookup_tables) = builder.create_lookup_tables(&tables, 0);
+    test.process_signed(&[create], &[&signers[0], &signers[2]])
+        .await
+        .unwrap();
+    let registry = test.registry().await;
+    let created = registry.tables.iter().map(|e| e.table).collect::<Vec<_>>();
+    assert_eq!(
+        created,
+        vec![lookup_table, lookup_tables[0], lookup_tables[1]]
+    );
+
+    // The signers leave multisig mode, and the authority signs alone again
+    let set_multisig = builder.set_multisig(&[], 0);
+    test.process_signed(&[set_multisig], &[&signers[0], &signers[2]])
+        .await
+        .unwrap();
+    let recent_slot = test.next_recent_slot().await;
+    let (create, _) = test.builder.create_lookup_table(recent_slot, 5, 0);
+    test.process(&[create]).await.unwrap();
+    assert_eq!(test.registry().await.len, 4);
+}
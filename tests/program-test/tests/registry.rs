@@ -0,0 +1,612 @@
+use lookup_table_registry::{
+    discriminator, ErrorCode, NewLookupTable, MAX_LABEL_LEN, MAX_REGISTRY_ENTRIES,
+};
+use lookup_table_registry_client::instructions::{
+    close_deactivated_table, prune_entry, InstructionBuilder,
+};
+use lookup_table_registry_program_test::{instruction_error, RegistryTest};
+use solana_sdk::{
+    account::Account, entrypoint::MAX_PERMITTED_DATA_INCREASE, pubkey::Pubkey, signer::Signer,
+    system_program,
+};
+
+#[tokio::test]
+async fn create_append_and_close_a_lookup_table() {
+    let mut test = RegistryTest::start().await;
+    let authority = test.authority.pubkey();
+    let registry_address = test.builder.registry_address();
+
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+    let registry = test.registry().await;
+    assert_eq!(registry.authority, authority);
+    assert_eq!((registry.len, registry.capacity), (0, 0));
+
+    // Create a lookup table, and append addresses with a duplicate to it
+    let recent_slot = test.next_recent_slot().await;
+    let (create, lookup_table) = test.builder.create_lookup_table(recent_slot, 2, 0);
+    test.process(&[create]).await.unwrap();
+    let mut addresses = (0..10).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+    addresses.push(addresses[0]);
+    let append = test
+        .builder
+        .append_to_lookup_table(lookup_table, &addresses, 2, true, 0);
+    test.process(&[append]).await.unwrap();
+
+    // The registry account is the authority of the lookup tables it creates
+    let table = test.lookup_table(lookup_table).await.unwrap();
+    assert_eq!(table.meta.authority, Some(registry_address));
+    assert_eq!(table.addresses.as_ref(), &addresses[..10]);
+    let registry = test.registry().await;
+    assert_eq!((registry.len, registry.capacity), (1, 1));
+    assert_eq!(registry.tables[0].table, lookup_table);
+    assert_eq!(registry.tables[0].discriminator, 2);
+    assert_eq!(registry.last_created_slot, recent_slot);
+
+    // Deactivate the lookup table
+    let slot = test.slot().await;
+    let remove = test.builder.remove_lookup_table(lookup_table, 0);
+    test.process(&[remove.clone()]).await.unwrap();
+    let registry = test.registry().await;
+    let entry = registry.tables[0];
+    assert_eq!(entry.discriminator, discriminator::DEACTIVATED);
+    assert!(entry.deactivation_slot >= slot);
+    assert_eq!((registry.len, registry.capacity), (1, 1));
+    let table = test.lookup_table(lookup_table).await.unwrap();
+    assert_eq!(table.meta.deactivation_slot, entry.deactivation_slot);
+
+    // The lookup table can't be closed until it has cooled down
+    let closeable_at_slot = entry.closeable_at_slot().unwrap();
+    test.warp_to_slot(closeable_at_slot - 1).await;
+    let error = test.process(&[remove.clone()]).await.unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        instruction_error(ErrorCode::TableStillCoolingDown)
+    );
+
+    // Close the lookup table, which returns its rent to the authority
+    test.warp_to_slot(closeable_at_slot).await;
+    let lamports = test.authority_lamports().await;
+    test.process(&[remove]).await.unwrap();
+    assert!(test.lookup_table(lookup_table).await.is_none());
+    assert!(test.authority_lamports().await > lamports);
+
+    // The entry is empty, but the registry keeps its capacity
+    let registry = test.registry().await;
+    assert_eq!((registry.len, registry.capacity), (0, 1));
+    assert_eq!(registry.tables[0].discriminator, discriminator::EMPTY);
+    assert_eq!(registry.tables[0].table, Pubkey::default());
+}
+
+#[tokio::test]
+async fn new_lookup_tables_reuse_empty_entries() {
+    let mut test = RegistryTest::start().await;
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+
+    let mut lookup_tables = vec![];
+    for discriminator in 2..5 {
+        let recent_slot = test.next_recent_slot().await;
+        let (create, lookup_table) =
+            test.builder
+                .create_lookup_table(recent_slot, discriminator, 0);
+        test.process(&[create]).await.unwrap();
+        lookup_tables.push(lookup_table);
+    }
+    let registry = test.registry().await;
+    assert_eq!((registry.len, registry.capacity), (3, 3));
+
+    // Remove the lookup table in the middle
+    let remove = test.builder.remove_lookup_table(lookup_tables[1], 0);
+    test.process(&[remove.clone()]).await.unwrap();
+    let closeable_at_slot = test.registry().await.tables[1].closeable_at_slot().unwrap();
+    test.warp_to_slot(closeable_at_slot).await;
+    test.process(&[remove]).await.unwrap();
+    let registry = test.registry().await;
+    assert_eq!((registry.len, registry.capacity), (2, 3));
+
+    // The first new lookup table takes the empty entry, and the next one is
+    // appended to the registry
+    for discriminator in 5..7 {
+        let recent_slot = test.next_recent_slot().await;
+        let (create, lookup_table) =
+            test.builder
+                .create_lookup_table(recent_slot, discriminator, 0);
+        test.process(&[create]).await.unwrap();
+        lookup_tables.push(lookup_table);
+    }
+    let registry = test.registry().await;
+    assert_eq!((registry.len, registry.capacity), (4, 4));
+    let tables = registry
+        .tables
+        .iter()
+        .map(|entry| (entry.table, entry.discriminator))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tables,
+        vec![
+            (lookup_tables[0], 2),
+            (lookup_tables[3], 5),
+            (lookup_tables[2], 4),
+            (lookup_tables[4], 6),
+        ]
+    );
+
+    // A recent slot can't be used again
+    let recent_slot = test.registry().await.last_created_slot;
+    let (create, _) = test.builder.create_lookup_table(recent_slot, 2, 0);
+    let error = test.process(&[create]).await.unwrap_err();
+    assert_eq!(error.unwrap(), instruction_error(ErrorCode::InvalidSlot));
+}
+
+#[tokio::test]
+async fn create_lookup_tables_together_or_with_addresses() {
+    let mut test = RegistryTest::start().await;
+    let registry_address = test.builder.registry_address();
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+
+    // Create several lookup tables in one instruction
+    let mut tables = vec![];
+    for discriminator in 2..5 {
+        tables.push(NewLookupTable {
+            recent_slot: test.next_recent_slot().await,
+            discriminator,
+        });
+    }
+    let (create, lookup_tables) = test.builder.create_lookup_tables(&tables, 0);
+    test.process(&[create]).await.unwrap();
+    let registry = test.registry().await;
+    assert_eq!((registry.len, registry.capacity), (3, 3));
+    assert_eq!(registry.last_created_slot, tables[2].recent_slot);
+    for ((entry, table), lookup_table) in registry.tables.iter().zip(&tables).zip(&lookup_tables) {
+        assert_eq!(entry.table, *lookup_table);
+        assert_eq!(entry.discriminator, table.discriminator);
+        let table = test.lookup_table(*lookup_table).await.unwrap();
+        assert_eq!(table.meta.authority, Some(registry_address));
+    }
+
+    // The recent slots must be increasing
+    let earlier_slot = test.next_recent_slot().await;
+    let later_slot = test.next_recent_slot().await;
+    let (create, _) = test.builder.create_lookup_tables(
+        &[
+            NewLookupTable {
+                recent_slot: later_slot,
+                discriminator: 2,
+            },
+            NewLookupTable {
+                recent_slot: earlier_slot,
+                discriminator: 2,
+            },
+        ],
+        0,
+    );
+    let error = test.process(&[create]).await.unwrap_err();
+    assert_eq!(error.unwrap(), instruction_error(ErrorCode::InvalidSlot));
+
+    // Create a lookup table with addresses, skipping the duplicates
+    let mut addresses = (0..5).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+    addresses.push(addresses[1]);
+    let recent_slot = test.next_recent_slot().await;
+    let (create, lookup_table) =
+        test.builder
+            .create_lookup_table_with_addresses(recent_slot, 5, &addresses, true, 0);
+    test.process(&[create]).await.unwrap();
+    let table = test.lookup_table(lookup_table).await.unwrap();
+    assert_eq!(table.addresses.as_ref(), &addresses[..5]);
+    let registry = test.registry().await;
+    assert_eq!((registry.len, registry.capacity), (4, 4));
+    assert_eq!(registry.tables[3].table, lookup_table);
+
+    // Duplicates fail unless they are skipped
+    let recent_slot = test.next_recent_slot().await;
+    let (create, _) =
+        test.builder
+            .create_lookup_table_with_addresses(recent_slot, 5, &addresses, false, 0);
+    let error = test.process(&[create]).await.unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        instruction_error(ErrorCode::DuplicateAddress)
+    );
+}
+
+#[tokio::test]
+async fn frozen_lookup_tables_are_kept() {
+    let mut test = RegistryTest::start().await;
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+
+    // The lookup table program only freezes lookup tables with addresses
+    let addresses = (0..3).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+    let recent_slot = test.next_recent_slot().await;
+    let (create, lookup_table) =
+        test.builder
+            .create_lookup_table_with_addresses(recent_slot, 2, &addresses, false, 0);
+    test.process(&[create]).await.unwrap();
+
+    let freeze = test.builder.freeze_lookup_table(lookup_table, 0);
+    test.process(&[freeze.clone()]).await.unwrap();
+    let registry = test.registry().await;
+    assert!(registry.tables[0].is_frozen());
+    assert_eq!(registry.tables[0].discriminator, 2 | discriminator::FROZEN);
+    let table = test.lookup_table(lookup_table).await.unwrap();
+    assert_eq!(table.meta.authority, None);
+
+    // A frozen lookup table can't be frozen again, extended or removed
+    let append =
+        test.builder
+            .append_to_lookup_table(lookup_table, &[Pubkey::new_unique()], 2, false, 0);
+    let remove = test.builder.remove_lookup_table(lookup_table, 0);
+    for instruction in [freeze, append, remove] {
+        let error = test.process(&[instruction]).await.unwrap_err();
+        assert_eq!(error.unwrap(), instruction_error(ErrorCode::TableFrozen));
+    }
+    let table = test.lookup_table(lookup_table).await.unwrap();
+    assert_eq!(table.addresses.as_ref(), &addresses[..]);
+}
+
+#[tokio::test]
+async fn import_lookup_tables_of_the_registry_or_its_authority() {
+    let mut test = RegistryTest::start().await;
+    let authority = test.authority.pubkey();
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+
+    // Lookup tables that the authority or the registry account is the
+    // authority of are imported
+    let registry_address = test.builder.registry_address();
+    let authority_table = test.create_lookup_table_of(authority).await;
+    let registry_table = test.create_lookup_table_of(registry_address).await;
+    for (lookup_table, discriminator) in [(authority_table, 2), (registry_table, 3)] {
+        let import = test
+            .builder
+            .import_lookup_table(lookup_table, discriminator, 0);
+        test.process(&[import]).await.unwrap();
+    }
+    let registry = test.registry().await;
+    assert_eq!((registry.len, registry.capacity), (2, 2));
+    let tables = registry
+        .tables
+        .iter()
+        .map(|entry| (entry.table, entry.discriminator))
+        .collect::<Vec<_>>();
+    assert_eq!(tables, vec![(authority_table, 2), (registry_table, 3)]);
+
+    // A lookup table is only imported once
+    let import = test.builder.import_lookup_table(authority_table, 4, 0);
+    let error = test.process(&[import]).await.unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        instruction_error(ErrorCode::DuplicateLookupTable)
+    );
+
+    // Lookup tables of other keys can't be imported
+    let other_table = test.create_lookup_table_of(Pubkey::new_unique()).await;
+    let import = test.builder.import_lookup_table(other_table, 2, 0);
+    let error = test.process(&[import]).await.unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        instruction_error(ErrorCode::InvalidLookupTable)
+    );
+
+    // The authority signs for the lookup tables that it is the authority of
+    let addresses = [Pubkey::new_unique()];
+    let append = test
+        .builder
+        .append_to_lookup_table(authority_table, &addresses, 2, false, 0);
+    test.process(&[append]).await.unwrap();
+    let table = test.lookup_table(authority_table).await.unwrap();
+    assert_eq!(table.addresses.as_ref(), &addresses[..]);
+    let remove = test.builder.remove_lookup_table(authority_table, 0);
+    test.process(&[remove]).await.unwrap();
+    let registry = test.registry().await;
+    assert_eq!(registry.tables[0].discriminator, discriminator::DEACTIVATED);
+}
+
+#[tokio::test]
+async fn set_the_label_and_program_of_lookup_tables() {
+    let mut test = RegistryTest::start().await;
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+    let recent_slot = test.next_recent_slot().await;
+    let (create, lookup_table) = test.builder.create_lookup_table(recent_slot, 2, 0);
+    test.process(&[create]).await.unwrap();
+
+    let program = Pubkey::new_unique();
+    let set_metadata = test
+        .builder
+        .set_entry_metadata(lookup_table, "swaps", program, 0);
+    test.process(&[set_metadata]).await.unwrap();
+    let registry = test.registry().await;
+    let metadata = registry.tables[0].metadata;
+    assert_eq!(metadata.label(), b"swaps");
+    assert_eq!(metadata.program, program);
+    assert!(metadata.created_slot > recent_slot);
+
+    // A shorter label replaces the whole label
+    let set_metadata = test
+        .builder
+        .set_entry_metadata(lookup_table, "swap", program, 0);
+    test.process(&[set_metadata]).await.unwrap();
+    assert_eq!(test.registry().await.tables[0].metadata.label(), b"swap");
+
+    // Labels are limited in length
+    let label = "a".repeat(MAX_LABEL_LEN + 1);
+    let set_metadata = test
+        .builder
+        .set_entry_metadata(lookup_table, &label, program, 0);
+    let error = test.process(&[set_metadata]).await.unwrap_err();
+    assert_eq!(error.unwrap(), instruction_error(ErrorCode::InvalidLabel));
+}
+
+#[tokio::test]
+async fn registries_in_namespaces_are_separate() {
+    let mut test = RegistryTest::start().await;
+    let authority = test.authority.pubkey();
+    let namespaced = InstructionBuilder::new(authority, authority).with_namespace("swaps");
+    assert_ne!(
+        namespaced.registry_address(),
+        test.builder.registry_address()
+    );
+
+    test.process(&[test.builder.init_registry(), namespaced.init_registry()])
+        .await
+        .unwrap();
+    let registry = test.registry_at(namespaced.registry_address()).await;
+    assert_eq!(registry.namespace(), b"swaps");
+    assert_eq!(registry.authority, authority);
+    assert_eq!(registry.seed_authority, authority);
+    assert_eq!(test.registry().await.namespace(), b"");
+
+    // Lookup tables are created in the registry of the namespace
+    let recent_slot = test.next_recent_slot().await;
+    let (create, lookup_table) = namespaced.create_lookup_table(recent_slot, 2, 0);
+    test.process(&[create]).await.unwrap();
+    let registry = test.registry_at(namespaced.registry_address()).await;
+    assert_eq!(registry.tables[0].table, lookup_table);
+    let table = test.lookup_table(lookup_table).await.unwrap();
+    assert_eq!(table.meta.authority, Some(namespaced.registry_address()));
+    assert_eq!(test.registry().await.len, 0);
+}
+
+#[tokio::test]
+async fn overflow_pages_are_used_and_closed_from_the_last_one() {
+    let mut test = RegistryTest::start().await;
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+    test.process(&[
+        test.builder.add_registry_page(1),
+        test.builder.add_registry_page(2),
+    ])
+    .await
+    .unwrap();
+    assert_eq!(test.registry().await.pages, 2);
+    let registry_address = test.builder.registry_address();
+    for page in 1..=2 {
+        let registry_page = test.registry_page(page).await;
+        assert_eq!(registry_page.registry, registry_address);
+        assert_eq!(registry_page.page, page);
+    }
+
+    // Create a lookup table in the first page
+    let recent_slot = test.next_recent_slot().await;
+    let (create, lookup_table) = test.builder.create_lookup_table(recent_slot, 2, 1);
+    test.process(&[create]).await.unwrap();
+    let registry_page = test.registry_page(1).await;
+    assert_eq!((registry_page.len, registry_page.capacity), (1, 1));
+    assert_eq!(registry_page.tables[0].table, lookup_table);
+    assert_eq!(test.registry().await.len, 0);
+
+    // The registry can't be closed while it has pages, and pages are closed
+    // from the last one once they are empty
+    let not_empty = instruction_error(ErrorCode::RegistryNotEmpty);
+    let error = test
+        .process(&[test.builder.close_registry()])
+        .await
+        .unwrap_err();
+    assert_eq!(error.unwrap(), not_empty);
+    let error = test
+        .process(&[test.builder.close_registry_page(1)])
+        .await
+        .unwrap_err();
+    assert_eq!(error.unwrap(), not_empty);
+    test.process(&[test.builder.close_registry_page(2)])
+        .await
+        .unwrap();
+    let error = test
+        .process(&[test.builder.close_registry_page(1)])
+        .await
+        .unwrap_err();
+    assert_eq!(error.unwrap(), not_empty);
+
+    // Close the lookup table, then the page and the registry
+    let remove = test.builder.remove_lookup_table(lookup_table, 1);
+    test.process(&[remove.clone()]).await.unwrap();
+    let closeable_at_slot = test.registry_page(1).await.tables[0]
+        .closeable_at_slot()
+        .unwrap();
+    test.warp_to_slot(closeable_at_slot).await;
+    test.process(&[remove]).await.unwrap();
+    test.process(&[
+        test.builder.close_registry_page(1),
+        test.builder.close_registry(),
+    ])
+    .await
+    .unwrap();
+    assert!(test.account(registry_address).await.is_none());
+    let page_address = test.builder.registry_page_address(1);
+    assert!(test.account(page_address).await.is_none());
+}
+
+#[tokio::test]
+async fn compact_a_registry_with_closed_lookup_tables() {
+    let mut test = RegistryTest::start().await;
+    let authority = test.authority.pubkey();
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+
+    let mut lookup_tables = vec![];
+    for discriminator in 2..6 {
+        let recent_slot = test.next_recent_slot().await;
+        let (create, lookup_table) =
+            test.builder
+                .create_lookup_table(recent_slot, discriminator, 0);
+        test.process(&[create]).await.unwrap();
+        lookup_tables.push(lookup_table);
+    }
+
+    // Close the first and the third lookup tables
+    let removes = [0, 2].map(|i| test.builder.remove_lookup_table(lookup_tables[i], 0));
+    test.process(&removes).await.unwrap();
+    let closeable_at_slot = test.registry().await.tables[2].closeable_at_slot().unwrap();
+    test.warp_to_slot(closeable_at_slot).await;
+    test.process(&removes).await.unwrap();
+    let size = test
+        .account(test.builder.registry_address())
+        .await
+        .unwrap()
+        .data
+        .len();
+    let registry = test.registry().await;
+    assert_eq!((registry.len, registry.capacity), (2, 4));
+
+    // The entries keep their order, and the rent of the empty entries goes to
+    // the authority
+    let lamports = test.authority_lamports().await;
+    let compact = test.builder.compact_registry(0, authority);
+    test.process(&[compact.clone()]).await.unwrap();
+    let registry = test.registry().await;
+    assert_eq!((registry.len, registry.capacity), (2, 2));
+    let tables = registry
+        .tables
+        .iter()
+        .map(|entry| entry.table)
+        .collect::<Vec<_>>();
+    assert_eq!(tables, vec![lookup_tables[1], lookup_tables[3]]);
+    let account = test.account(test.builder.registry_address()).await.unwrap();
+    assert!(account.data.len() < size);
+    assert!(test.authority_lamports().await > lamports);
+
+    // Compacting a registry without empty entries does nothing
+    test.process(&[compact]).await.unwrap();
+    let registry = test.registry().await;
+    assert_eq!((registry.len, registry.capacity), (2, 2));
+}
+
+#[tokio::test]
+async fn registry_accounts_grow_past_the_size_they_can_be_created_with() {
+    let mut test = RegistryTest::start().await;
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+    assert_eq!(test.fill_registry(MAX_REGISTRY_ENTRIES - 1).await, 0);
+
+    // The last entry fits in the registry account, which is larger than an
+    // account created by a program can be
+    let recent_slot = test.next_recent_slot().await;
+    let (create, _) = test.builder.create_lookup_table(recent_slot, 2, 0);
+    test.process(&[create]).await.unwrap();
+    let registry = test.registry().await;
+    assert_eq!(registry.len as usize, MAX_REGISTRY_ENTRIES);
+    let account = test.account(test.builder.registry_address()).await.unwrap();
+    assert!(account.data.len() > MAX_PERMITTED_DATA_INCREASE);
+
+    let recent_slot = test.next_recent_slot().await;
+    let (create, _) = test.builder.create_lookup_table(recent_slot, 2, 0);
+    let error = test.process(&[create]).await.unwrap_err();
+    assert_eq!(error.unwrap(), instruction_error(ErrorCode::TooManyEntries));
+}
+
+#[tokio::test]
+async fn anyone_can_close_a_lookup_table_that_closes_automatically() {
+    let mut test = RegistryTest::start().await;
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+    let registry = test.builder.registry_address();
+
+    let recent_slot = test.next_recent_slot().await;
+    let (create, lookup_table) = test.builder.create_lookup_table(recent_slot, 2, 0);
+    let recipient = Pubkey::new_unique();
+    let set_auto_close = test
+        .builder
+        .set_auto_close(lookup_table, Some(recipient), 0);
+    test.process(&[create, set_auto_close]).await.unwrap();
+    assert_eq!(test.registry().await.tables[0].close_recipient, recipient);
+
+    // The lookup table must be deactivated by the authority first
+    let close = close_deactivated_table(registry, None, lookup_table, recipient);
+    let error = test.process(&[close.clone()]).await.unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        instruction_error(ErrorCode::InvalidDiscriminator)
+    );
+    test.process(&[test.builder.remove_lookup_table(lookup_table, 0)])
+        .await
+        .unwrap();
+    let closeable_at_slot = test.registry().await.tables[0].closeable_at_slot().unwrap();
+    test.warp_to_slot(closeable_at_slot).await;
+
+    // The lamports can only go to the close recipient
+    let other = close_deactivated_table(registry, None, lookup_table, Pubkey::new_unique());
+    let error = test.process(&[other]).await.unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        instruction_error(ErrorCode::InvalidRecipient)
+    );
+
+    test.process(&[close]).await.unwrap();
+    assert!(test.lookup_table(lookup_table).await.is_none());
+    assert!(test.account(recipient).await.unwrap().lamports > 0);
+    let registry = test.registry().await;
+    assert_eq!((registry.len, registry.capacity), (0, 1));
+    assert_eq!(registry.tables[0].discriminator, discriminator::EMPTY);
+    assert_eq!(registry.tables[0].close_recipient, Pubkey::default());
+}
+
+#[tokio::test]
+async fn anyone_can_prune_entries_of_lookup_tables_closed_outside_the_registry() {
+    let mut test = RegistryTest::start().await;
+    test.process(&[test.builder.init_registry()]).await.unwrap();
+    let registry = test.builder.registry_address();
+
+    let mut lookup_tables = vec![];
+    for discriminator in 2..5 {
+        let recent_slot = test.next_recent_slot().await;
+        let (create, lookup_table) =
+            test.builder
+                .create_lookup_table(recent_slot, discriminator, 0);
+        test.process(&[create]).await.unwrap();
+        lookup_tables.push(lookup_table);
+    }
+
+    // A lookup table that exists can't be pruned
+    let error = test
+        .process(&[prune_entry(registry, None, lookup_tables[0])])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.unwrap(),
+        instruction_error(ErrorCode::LookupTableExists)
+    );
+
+    // The first lookup table is gone, and the address of the second one is
+    // now a system account
+    test.context
+        .set_account(&lookup_tables[0], &Account::default().into());
+    test.context.set_account(
+        &lookup_tables[1],
+        &Account::new(1_000_000, 0, &system_program::ID).into(),
+    );
+    test.process(&[
+        prune_entry(registry, None, lookup_tables[0]),
+        prune_entry(registry, None, lookup_tables[1]),
+    ])
+    .await
+    .unwrap();
+
+    let registry = test.registry().await;
+    assert_eq!((registry.len, registry.capacity), (1, 3));
+    let tables = registry
+        .tables
+        .iter()
+        .map(|entry| (entry.table, entry.discriminator))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tables,
+        vec![
+            (Pubkey::default(), discriminator::EMPTY),
+            (Pubkey::default(), discriminator::EMPTY),
+            (lookup_tables[2], 4),
+        ]
+    );
+}